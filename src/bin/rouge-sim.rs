@@ -0,0 +1,321 @@
+/*
+ * rouge-sim: plays lots of seeded games with a simple scripted player and
+ * reports how they went, for judging balance changes with numbers.
+ *
+ * Run it from the repository root so it finds `data/`:
+ *     cargo run --release --bin rouge-sim -- --games 1000 --seed 0
+ */
+
+extern crate rouge;
+
+use std::collections::{BTreeMap, VecDeque};
+
+use rouge::types::*;
+use rouge::func::*;
+use rouge::r#const::*;
+
+// drink a healing potion below this percentage of max HP
+const HEAL_THRESHOLD: i32 = 40;
+// use scrolls on monsters with more HP than this many of the player's hits
+const TOUGH_MONSTER_HITS: i32 = 3;
+const MAX_INVENTORY: usize = 26;
+
+struct Options {
+    games: u64,
+    seed: u64,
+    max_commands: usize,
+}
+
+/// How one simulated game went
+struct GameReport {
+    /// the depth the player died on, or None if they were still alive at the end
+    death_depth: Option<u32>,
+    /// whatever hit the player last
+    cause_of_death: Option<String>,
+    /// turns taken on each depth, counting every visit
+    turns_per_level: BTreeMap<u32, u32>,
+    /// (character level, total xp) on arriving at each depth after the first
+    xp_on_arrival: BTreeMap<u32, (i32, i32)>,
+    items_found: Vec<String>,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let arg_value = |name: &str, default: u64| {
+        args.iter().position(|arg| arg == name)
+            .and_then(|i| args.get(i + 1))
+            .map_or(default, |value| value.parse().unwrap_or_else(|_| {
+                eprintln!("{} needs a number", name);
+                std::process::exit(1);
+            }))
+    };
+    let options = Options {
+        games: arg_value("--games", 1000),
+        seed: arg_value("--seed", 0),
+        max_commands: arg_value("--max-commands", 20000) as usize,
+    };
+
+    let templates = load_templates().expect("Game data not found");
+    let reports: Vec<GameReport> = (0..options.games)
+        .map(|game| play(options.seed + game, &templates, options.max_commands))
+        .collect();
+    print_report(&options, &reports);
+}
+
+/// Play one game with the scripted policy until the player dies or runs out of commands
+fn play(seed: u64, templates: &Templates, max_commands: usize) -> GameReport {
+    let mut state = GameState::new(seed, templates.clone());
+    let mut report = GameReport {
+        death_depth: None,
+        cause_of_death: None,
+        turns_per_level: BTreeMap::new(),
+        xp_on_arrival: BTreeMap::new(),
+        items_found: vec![],
+    };
+
+    while state.game.history.len() < max_commands {
+        let command = choose_command(&state);
+        for event in state.step(command) {
+            match event {
+                GameEvent::Message(text, _) => {
+                    if text.starts_with("You picked up a ") {
+                        report.items_found.push(text["You picked up a ".len()..text.len() - 1].to_string());
+                    }
+                }
+                // the level the turn ended on, which is where the monsters moved
+                GameEvent::TurnTaken => {
+                    *report.turns_per_level.entry(state.game.dungeon_level).or_insert(0) += 1;
+                }
+                GameEvent::LevelChanged(depth) => {
+                    let player = state.player();
+                    report.xp_on_arrival.insert(depth, (player.level, total_xp(player)));
+                }
+                GameEvent::PlayerDied(cause) => {
+                    report.death_depth = Some(state.game.dungeon_level);
+                    report.cause_of_death = Some(cause);
+                }
+                GameEvent::LevelUpReady | GameEvent::SaveDue => {}
+            }
+        }
+        if report.death_depth.is_some() {
+            break;
+        }
+    }
+    report
+}
+
+/// all the experience the player has earned, including what was spent on levelling up
+fn total_xp(player: &Object) -> i32 {
+    let spent: i32 = (1..player.level).map(|level| LEVEL_UP_BASE + level * LEVEL_UP_FACTOR).sum();
+    spent + player.fighter.map_or(0, |f| f.xp)
+}
+
+/// The scripted policy: level up, heal when hurt, equip whatever fits an
+/// empty slot, fight anything in sight (with scrolls for tough monsters),
+/// hunt down the rest of the level and pick up items, then take the stairs.
+fn choose_command(state: &GameState) -> Command {
+    let player = state.player();
+    let game = &state.game;
+
+    if state.level_up_pending() {
+        let stat = match player.level % 3 {
+            0 => Stat::Constitution,
+            1 => Stat::Strength,
+            _ => Stat::Agility,
+        };
+        return Command::LevelUp(stat);
+    }
+
+    let find_item = |use_effect: Item| {
+        game.inventory.iter().position(|item| item.item == Some(use_effect))
+    };
+    let hp = player.fighter.map_or(0, |f| f.hp);
+    if hp * 100 < player.max_hp(game) * HEAL_THRESHOLD {
+        if let Some(potion) = find_item(Item::Heal) {
+            return Command::UseItem { inventory_id: potion, target: None };
+        }
+    }
+
+    for (inventory_id, item) in game.inventory.iter().enumerate() {
+        if let Some(equipment) = item.equipment {
+            if !equipment.equipped && get_equipped_in_slot(equipment.slot, &game.inventory).is_none() {
+                return Command::UseItem { inventory_id, target: None };
+            }
+        }
+    }
+
+    // fight the nearest monster in sight
+    let nearest = state.objects.with_ai()
+        .filter(|&(_, monster)| state.fov.is_in_fov(monster.x, monster.y))
+        .min_by_key(|&(_, monster)| (player.distance_to(monster) * 100.0) as i32)
+        .map(|(_, monster)| monster);
+    if let Some(monster) = nearest {
+        let (dx, dy) = (monster.x - player.x, monster.y - player.y);
+        let distance = player.distance_to(monster);
+        let monster_hp = monster.fighter.map_or(0, |f| f.hp);
+        if monster_hp > player.power(game) * TOUGH_MONSTER_HITS {
+            let target = Some(monster.pos());
+            let confused = match monster.ai {
+                Some(Ai::Confused { .. }) => true,
+                _ => false,
+            };
+            if let (Some(scroll), true) = (find_item(Item::Lightning), distance <= LIGHTNING_RANGE as f32) {
+                return Command::UseItem { inventory_id: scroll, target: None };
+            }
+            if let (Some(scroll), true) = (find_item(Item::Fireball), distance > FIREBALL_RADIUS as f32) {
+                return Command::UseItem { inventory_id: scroll, target };
+            }
+            if let (Some(scroll), false) = (find_item(Item::Confuse), confused) {
+                if distance <= CONFUSE_RANGE as f32 {
+                    return Command::UseItem { inventory_id: scroll, target };
+                }
+            }
+        }
+        if dx.abs() <= 1 && dy.abs() <= 1 {
+            return Command::Move { dx, dy };
+        }
+    }
+
+    let room_in_inventory = game.inventory.len() < MAX_INVENTORY;
+    let item_at = |x: i32, y: i32| {
+        room_in_inventory && state.objects.with_item().any(|(_, item)| item.pos() == (x, y))
+    };
+    if item_at(player.x, player.y) {
+        return Command::PickUp;
+    }
+
+    // clear the level: go for the nearest monster or item
+    let monster_at = |x: i32, y: i32| state.objects.with_ai().any(|(_, monster)| monster.pos() == (x, y));
+    if let Some((dx, dy)) = first_step(state, |x, y| monster_at(x, y) || item_at(x, y)) {
+        return Command::Move { dx, dy };
+    }
+
+    // then head down
+    let stairs_at = |x: i32, y: i32| {
+        state.objects.iter().any(|(_, object)| object.name == "stairs" && object.pos() == (x, y))
+    };
+    if stairs_at(player.x, player.y) {
+        return Command::Descend;
+    }
+    match first_step(state, stairs_at) {
+        Some((dx, dy)) => Command::Move { dx, dy },
+        None => Command::Wait,
+    }
+}
+
+/// The first step along the shortest path from the player to the nearest tile
+/// `is_goal` is true for, going around walls and anything else in the way.
+/// Doors are walked through, since walking into one opens it, but lava and
+/// chasms are gone around.
+fn first_step<F: Fn(i32, i32) -> bool>(state: &GameState, is_goal: F) -> Option<(i32, i32)> {
+    let start = state.player().pos();
+    let has_key = state.game.inventory.iter().any(|item| item.item == Some(Item::Key));
+    // where each tile was first reached from
+    let mut came_from = vec![vec![None; MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+    let mut queue = VecDeque::new();
+    came_from[start.0 as usize][start.1 as usize] = Some(start);
+    queue.push_back(start);
+
+    while let Some((x, y)) = queue.pop_front() {
+        if (x, y) != start && is_goal(x, y) {
+            // walk back to the tile next to the player
+            let mut step = (x, y);
+            while let Some(previous) = came_from[step.0 as usize][step.1 as usize] {
+                if previous == start {
+                    return Some((step.0 - start.0, step.1 - start.1));
+                }
+                step = previous;
+            }
+        }
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let (next_x, next_y) = (x + dx, y + dy);
+                if next_x < 0 || next_y < 0 || next_x >= MAP_WIDTH || next_y >= MAP_HEIGHT {
+                    continue;
+                }
+                if came_from[next_x as usize][next_y as usize].is_some() {
+                    continue;
+                }
+                // goals can be in the way (a monster to attack), anything else can't
+                let tile = &state.game.map[next_x as usize][next_y as usize];
+                let door_opens = match tile.door {
+                    Some(Door::Closed) => true,
+                    Some(Door::Locked) => has_key,
+                    _ => false,
+                };
+                let passable = tile.kind.is_safe() && (door_opens ||
+                    !is_blocked(next_x, next_y, &state.game.map, &state.objects) ||
+                    (!tile.blocked && is_goal(next_x, next_y)));
+                if passable {
+                    came_from[next_x as usize][next_y as usize] = Some((x, y));
+                    queue.push_back((next_x, next_y));
+                }
+            }
+        }
+    }
+    None
+}
+
+fn print_report(options: &Options, reports: &[GameReport]) {
+    let games = reports.len() as f32;
+    println!("Simulated {} games from seed {} (at most {} commands each)\n",
+             reports.len(), options.seed, options.max_commands);
+
+    println!("Death depth:");
+    let mut deaths = BTreeMap::new();
+    for report in reports {
+        *deaths.entry(report.death_depth).or_insert(0) += 1;
+    }
+    for (depth, &count) in &deaths {
+        let label = depth.map_or("alive".to_string(), |depth| format!("{:5}", depth));
+        println!("  {}  {:6} {:5.1}%  {}", label, count, count as f32 / games * 100.0,
+                 "#".repeat((count as f32 / games * 50.0).round() as usize));
+    }
+
+    println!("\nCause of death:");
+    let mut causes = BTreeMap::new();
+    for cause in reports.iter().filter_map(|report| report.cause_of_death.as_ref()) {
+        *causes.entry(cause.clone()).or_insert(0) += 1;
+    }
+    let mut causes: Vec<_> = causes.into_iter().collect();
+    causes.sort_by(|a, b| b.1.cmp(&a.1));
+    for (cause, count) in causes {
+        println!("  {:24} {:6} {:5.1}%", cause, count, count as f32 / games * 100.0);
+    }
+
+    println!("\nXP on arriving at each depth (mean character level, mean total xp, games):");
+    let mut arrivals: BTreeMap<u32, Vec<(i32, i32)>> = BTreeMap::new();
+    for report in reports {
+        for (&depth, &xp) in &report.xp_on_arrival {
+            arrivals.entry(depth).or_insert_with(Vec::new).push(xp);
+        }
+    }
+    for (depth, xp) in &arrivals {
+        let count = xp.len() as f32;
+        let level = xp.iter().map(|&(level, _)| level as f32).sum::<f32>() / count;
+        let total = xp.iter().map(|&(_, total)| total as f32).sum::<f32>() / count;
+        println!("  {:5}  {:6.2} {:8.1} {:6}", depth, level, total, xp.len());
+    }
+
+    println!("\nTurns per depth (mean, median, games):");
+    let mut turns: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+    for report in reports {
+        for (&depth, &level_turns) in &report.turns_per_level {
+            turns.entry(depth).or_insert_with(Vec::new).push(level_turns);
+        }
+    }
+    for (depth, turns) in &mut turns {
+        turns.sort();
+        let mean = turns.iter().sum::<u32>() as f32 / turns.len() as f32;
+        println!("  {:5}  {:8.1} {:6} {:6}", depth, mean, turns[turns.len() / 2], turns.len());
+    }
+
+    println!("\nItems found (per game):");
+    let mut items = BTreeMap::new();
+    for item in reports.iter().flat_map(|report| report.items_found.iter()) {
+        *items.entry(item.clone()).or_insert(0) += 1;
+    }
+    for (item, count) in items {
+        println!("  {:24} {:6.2}", item, count as f32 / games);
+    }
+}
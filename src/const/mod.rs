@@ -1,20 +1,32 @@
-use super::*;
+use crate::types::Color;
 
-use tcod::map::FovAlgorithm;
-use tcod::Color;
+use crate::types::EntityId;
 
 pub const CORPSE: char = 1u8 as char;
 pub const TROLL: char = 161u8 as char;
 pub const ORC: char = 160u8 as char;
 pub const WALL: char = 164u8 as char;
 pub const FLOOR: char = 178u8 as char;
+// plain ASCII for the glyphs above (and the potions' in data/items.json), for
+// fonts and terminals that can't draw them the way DarkondDigsDeeper does
+pub const ASCII_GLYPHS: &[(char, char)] = &[
+    (CORPSE, '%'),
+    (WALL, '#'),
+    (FLOOR, '.'),
+    (ORC, 'o'),
+    (TROLL, 'T'),
+    ('\u{14}', '!'),
+];
+pub const CLOSED_DOOR: char = '+';
+pub const OPEN_DOOR: char = '\'';
+pub const TRAP: char = '^';
 
 pub const INVENTORY_WIDTH: i32 = 50;
 pub const LEVEL_SCREEN_WIDTH: i32 = 40;
 pub const CHARACTER_SCREEN_WIDTH: i32 = 40;
 
-// player will always be the first object
-pub const PLAYER: usize = 0;
+// player will always be the first object spawned
+pub const PLAYER: EntityId = EntityId { index: 0, generation: 0 };
 
 // experience and level-ups
 pub const LEVEL_UP_BASE: i32 = 200;
@@ -46,10 +58,71 @@ pub const CONFUSE_NUM_TURNS: i32 = 5;
 pub const CONFUSE_RANGE: i32 = 20;
 
 
+// Game data
+pub const MONSTERS_FILE: &str = "./data/monsters.json";
+pub const ITEMS_FILE: &str = "./data/items.json";
+// the fonts that can be picked from, and where their images are
+pub const FONTS_FILE: &str = "./data/fonts.json";
+pub const FONTS_DIR: &str = "./fonts";
+// hand-drawn rooms, one per file, and how many spots to try fitting each one in
+pub const PREFABS_DIR: &str = "./prefabs";
+pub const PREFAB_ATTEMPTS: i32 = 50;
+// each character is saved to its own file in the save directory
+pub const SAVE_EXTENSION: &str = "sav";
+// older versions saved the one game to the working directory
+pub const LEGACY_SAVE_FILE: &str = "savegame";
+// bump this (and add a migration) whenever the saved data changes
+pub const SAVE_VERSION: u32 = 7;
+// the game is saved this often (in turns) as well as on every new level
+pub const AUTOSAVE_TURNS: u32 = 100;
+// how many saves the menu lists at once, leaving a letter for the next page
+pub const SAVES_PER_PAGE: usize = 25;
+// the player's preferences, in the config directory
+pub const SETTINGS_FILE: &str = "settings.json";
+pub const KEYMAP_FILE: &str = "keymap.json";
+// compressed saves start with these bytes (the gzip header)
+pub const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+// for characters from before they had names
+pub const DEFAULT_NAME: &str = "Adventurer";
+pub const MAX_NAME_LENGTH: usize = 20;
+// a replay of each game is written to this directory in the save directory,
+// named after its seed, the character and when it was written
+pub const REPLAY_DIR: &str = "replays";
+// the player starts out with one of these equipped
+pub const STARTING_ITEM: &str = "dagger";
+
 // Room Generation
 pub const ROOM_MAX_SIZE: i32 = 10;
 pub const ROOM_MIN_SIZE: i32 = 6;
 pub const MAX_ROOMS: i32 = 30;
+// how many times to try laying out a usable level before settling for plain rooms
+pub const MAP_ATTEMPTS: i32 = 10;
+// the chance, in percent, of a door where a tunnel meets a room, and of a door being locked
+pub const DOOR_CHANCE: u32 = 60;
+pub const LOCKED_DOOR_CHANCE: u32 = 15;
+pub const SECRET_DOOR_CHANCE: u32 = 10;
+// locked doors are opened with this item, one key per door
+pub const KEY_ITEM: &str = "key";
+// the most tiles a patch of water, lava, chasm or rubble covers
+pub const MAX_TERRAIN_SIZE: i32 = 12;
+// what lava does to whoever steps in it, and falling down a chasm to the player
+pub const LAVA_DAMAGE: i32 = 10;
+pub const FALL_DAMAGE: i32 = 5;
+// the chance, in percent, of deep water taking each weapon or piece of armour that isn't equipped
+pub const SWEEP_AWAY_CHANCE: u32 = 25;
+// what a dart trap does to whoever sets it off
+pub const DART_DAMAGE: i32 = 6;
+// the chance, in percent, of a level having a treasure room hidden behind a secret door,
+// how many spots to try fitting one in, and the most items in it
+pub const SECRET_ROOM_CHANCE: u32 = 30;
+pub const SECRET_ROOM_ATTEMPTS: i32 = 50;
+pub const MAX_SECRET_ROOM_ITEMS: i32 = 3;
+// the chance, in percent, of spotting each hidden trap or secret door next to the player
+// as they walk past, and of finding each one with a turn spent searching: a base chance
+// plus some for every point of agility
+pub const NOTICE_CHANCE: u32 = 10;
+pub const SEARCH_CHANCE: u32 = 20;
+pub const SEARCH_CHANCE_PER_AGILITY: u32 = 10;
 
 // Map 
 pub const MAP_WIDTH: i32 = 80;
@@ -59,7 +132,9 @@ pub const COLOR_DARK_WALL: Color = Color { r: 64, g: 64, b: 64 };
 pub const COLOR_LIGHT_WALL: Color = Color { r: 130, g: 110, b: 50 };
 pub const COLOR_DARK_GROUND: Color = Color { r: 96, g: 96, b: 96 };
 pub const COLOR_LIGHT_GROUND: Color = Color { r: 200, g: 180, b: 50 };
+pub const COLOR_DARK_DOOR: Color = Color { r: 80, g: 50, b: 20 };
+pub const COLOR_LIGHT_DOOR: Color = Color { r: 160, g: 100, b: 40 };
+pub const COLOR_LOCKED_DOOR: Color = Color { r: 200, g: 60, b: 40 };
 
-pub const FOV_ALGO: FovAlgorithm = FovAlgorithm::Basic;
 pub const FOV_LIGHT_WALLS: bool = true;
 pub const TORCH_RADIUS: i32 = 15;
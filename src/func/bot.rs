@@ -0,0 +1,148 @@
+use std::io::{self, BufRead, Write};
+
+use crate::types::*;
+use crate::r#const::*;
+use super::{level_up_xp, save_replay};
+
+/// What the player can see right now, plus any messages logged since `log_start`
+pub fn observe(state: &GameState, log_start: usize) -> Observation {
+    let game = &state.game;
+    let player = state.player();
+    let fighter = player.fighter;
+
+    let mut tiles = vec![];
+    for y in 0..MAP_HEIGHT {
+        for x in 0..MAP_WIDTH {
+            if state.fov.is_in_fov(x, y) {
+                let tile = &game.map[x as usize][y as usize];
+                tiles.push(VisibleTile { x, y, blocked: tile.blocked, door: tile.door.filter(|&door| door != Door::Secret),
+                                         kind: tile.kind,
+                                         trap: tile.trap.filter(|trap| !trap.hidden).map(|trap| trap.kind) });
+            }
+        }
+    }
+
+    let objects = state.objects.iter()
+        .filter(|&(id, object)| id != PLAYER && state.fov.is_in_fov(object.x, object.y))
+        .map(|(_, object)| VisibleObject {
+            name: object.name.clone(),
+            x: object.x,
+            y: object.y,
+            hp: object.fighter.map(|f| f.hp),
+            max_hp: object.fighter.map(|_| object.max_hp(game)),
+            item: object.item.is_some(),
+        }).collect();
+
+    let inventory = game.inventory.iter().map(|item| InventoryEntry {
+        name: item.name.clone(),
+        equipped: item.equipment.map_or(false, |e| e.equipped),
+        targeting: item.item.map_or(Targeting::None, |item| item.targeting()),
+    }).collect();
+
+    Observation {
+        turn: game.history.len(),
+        dungeon_level: game.dungeon_level,
+        player: PlayerStats {
+            x: player.x,
+            y: player.y,
+            alive: player.alive,
+            hp: fighter.map_or(0, |f| f.hp),
+            max_hp: player.max_hp(game),
+            power: player.power(game),
+            defense: player.defense(game),
+            level: player.level,
+            xp: fighter.map_or(0, |f| f.xp),
+            xp_to_level_up: level_up_xp(player),
+        },
+        tiles,
+        objects,
+        inventory,
+        log: game.log[log_start..].iter().map(|&(ref msg, _)| msg.clone()).collect(),
+        level_up_pending: state.level_up_pending(),
+    }
+}
+
+/// Turn a bot's command into a game command, or None if it wants to stop
+pub fn bot_command(command: BotCommand, state: &GameState) -> Result<Option<Command>, String> {
+    let command = match command {
+        BotCommand::Move { dx, dy } => Command::Move { dx, dy },
+        BotCommand::Attack { x, y } => {
+            let (dx, dy) = (x - state.player().x, y - state.player().y);
+            if dx.abs() > 1 || dy.abs() > 1 || (dx, dy) == (0, 0) {
+                return Err(format!("({}, {}) isn't next to the player", x, y));
+            }
+            // attacking is just moving into the monster
+            Command::Move { dx, dy }
+        }
+        BotCommand::Use { item, target } => {
+            if item >= state.game.inventory.len() {
+                return Err(format!("there's no item {} in the inventory", item));
+            }
+            Command::UseItem { inventory_id: item, target }
+        }
+        BotCommand::Drop { item } => {
+            if item >= state.game.inventory.len() {
+                return Err(format!("there's no item {} in the inventory", item));
+            }
+            Command::DropItem { inventory_id: item }
+        }
+        BotCommand::PickUp => Command::PickUp,
+        BotCommand::Descend => Command::Descend,
+        BotCommand::Ascend => Command::Ascend,
+        BotCommand::Close { dx, dy } => Command::CloseDoor { dx, dy },
+        BotCommand::Search => Command::Search,
+        BotCommand::Wait => Command::Wait,
+        BotCommand::LevelUp { stat } => Command::LevelUp(stat),
+        BotCommand::Quit => return Ok(None),
+    };
+    Ok(Some(command))
+}
+
+/// Play a game over stdin and stdout: write an observation as a line of JSON,
+/// read a command back as a line of JSON, and repeat until the player dies,
+/// the bot sends `quit` or stdin is closed. Bad commands get an
+/// `{"error": ...}` line and the bot can try again.
+pub fn run_bot(state: &mut GameState) -> io::Result<()> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let mut lines = stdin.lock().lines();
+    let mut log_start = 0;
+
+    loop {
+        let observation = observe(state, log_start);
+        log_start = state.game.log.len();
+        writeln!(stdout, "{}", serde_json::to_string(&observation)?)?;
+        stdout.flush()?;
+        if !state.player().alive {
+            break;
+        }
+
+        // keep reading until there's a command that makes sense
+        let command = loop {
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => break None,
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let result = serde_json::from_str(&line).map_err(|e| e.to_string())
+                .and_then(|command| bot_command(command, state));
+            match result {
+                Ok(command) => break command,
+                Err(e) => {
+                    writeln!(stdout, "{}", json!({ "error": e }))?;
+                    stdout.flush()?;
+                }
+            }
+        };
+        match command {
+            Some(command) => { state.step(command); }
+            None => break,
+        }
+    }
+    // a replay lets a bot's game be watched afterwards
+    save_replay(state).ok();
+    Ok(())
+}
@@ -1,84 +1,34 @@
+use std::collections::VecDeque;
+
 use super::*;
 use rand::Rng;
 use crate::r#const::*;
 use crate::types::*;
-use crate::types::Tcod;
 use crate::types::Messages;
 use crate::func::*;
 use crate::types::object::Object;
-use tcod::input::{self, Event, Mouse};
-use tcod::colors::{self, Color};
+use crate::types::colors;
 
-/// returns a clicked monster inside FOV up to a range, or None if right-clicked
-pub fn target_monster(tcod: &mut Tcod,
-                  objects: &[Object],
-                  game: &mut Game,
-                  max_range: Option<f32>)
-    -> Option<usize> {
-        loop {
-            match target_tile(tcod, objects, game, max_range) {
-                Some((x, y)) => {
-                    // return the first clicked monster, otherwise continue looping
-                    for (id, obj) in objects.iter().enumerate() {
-                        if obj.pos() == (x, y) && obj.fighter.is_some() && id != PLAYER {
-                            return Some(id)
-                        }
-                    }
-                }
-                None => return None,
-            }
-        }
-    }
-
-/// return the position of a tile left-clicked in player's FOV (optionally in a
-/// range), or (None,None) if right-clicked.
-pub fn target_tile(tcod: &mut Tcod,
-               objects: &[Object], game: &mut Game,
-               max_range: Option<f32>)
-    -> Option<(i32, i32)> {
-        use tcod::input::KeyCode::Escape;
-        loop {
-            // render the screen. this erases the inventory and shows the names of
-            // objects under the mouse.
-            tcod.root.flush();
-
-
-            let event = input::check_for_event(input::KEY_PRESS | input::MOUSE).map(|e| e.1);
-            let mut key = None;
-            match event {
-                Some(Event::Mouse(m)) => tcod.mouse = m,
-                Some(Event::Key(k)) => key = Some(k),
-                None => {}
-            }
-            render_all(tcod, objects, game, false);
-
-            let (x, y) = (tcod.mouse.cx as i32, tcod.mouse.cy as i32);
-
-            // accept the target if the player clicked in FOV, and in case a range
-            // is specified, if it's in that range
-            let in_fov = (x < MAP_WIDTH) && (y < MAP_HEIGHT) && tcod.fov.is_in_fov(x, y);
-            let in_range = max_range.map_or(
-                true, |range| objects[PLAYER].distance(x, y) <= range);
-            if tcod.mouse.lbutton_pressed && in_fov && in_range {
-                return Some((x, y))
-            }
-
-            let escape = key.map_or(false, |k| k.code == Escape);
-            if tcod.mouse.rbutton_pressed || escape {
-                return None  // cancel if the player right-clicked or pressed Escape
-            }
-
-        }
+/// returns the monster on a tile inside FOV, optionally up to a range from the player
+pub fn monster_at(x: i32, y: i32, max_range: Option<f32>, objects: &Entities, fov_map: &FovMap)
+    -> Option<EntityId> {
+    let in_range = max_range.map_or(true, |range| objects[PLAYER].distance(x, y) <= range);
+    if !in_range || !fov_map.is_in_fov(x, y) {
+        return None
     }
+    objects.with_fighter()
+        .find(|&(id, obj)| obj.pos() == (x, y) && id != PLAYER)
+        .map(|(id, _)| id)
+}
 
 /// find closest enemy, up to a maximum range, and in the player's FOV
-pub fn closest_monster(max_range: i32, objects: &mut [Object], tcod: &Tcod) -> Option<usize> {
+pub fn closest_monster(max_range: i32, objects: &Entities, fov_map: &FovMap) -> Option<EntityId> {
     let mut closest_enemy = None;
     let mut closest_dist = (max_range + 1) as f32;  // start with (slightly more than) maximum range
 
-    for (id, object) in objects.iter().enumerate() {
-        if (id != PLAYER) && object.fighter.is_some() && object.ai.is_some() &&
-            tcod.fov.is_in_fov(object.x, object.y)
+    for (id, object) in objects.with_fighter() {
+        if (id != PLAYER) && object.ai.is_some() &&
+            fov_map.is_in_fov(object.x, object.y)
             {
                 // calculate distance between this object and the player
                 let dist = objects[PLAYER].distance_to(object);
@@ -92,24 +42,23 @@ pub fn closest_monster(max_range: i32, objects: &mut [Object], tcod: &Tcod) -> O
 }
 
 
-pub fn cast_fireball(_inventory_id: usize, objects: &mut [Object],game: &mut Game, tcod: &mut Tcod)
+pub fn cast_fireball(_inventory_id: usize, target: Option<(i32, i32)>, objects: &mut Entities,
+                     game: &mut Game, fov_map: &FovMap)
     -> UseResult
 {
-    // ask the player for a target tile to throw a fireball at
-    game.log.add("Left-click a target tile for the fireball, or right-click to cancel.",
-                 colors::LIGHT_CYAN);
-    let (x, y) = match target_tile(tcod, objects, game, None) {
-        Some(tile_pos) => tile_pos,
-        None => return UseResult::Cancelled,
+    // the fireball can only be thrown at a tile the player can see
+    let (x, y) = match target {
+        Some((x, y)) if fov_map.is_in_fov(x, y) => (x, y),
+        _ => return UseResult::Cancelled,
     };
     game.log.add(format!("The fireball explodes, burning everything within {} tiles!", FIREBALL_RADIUS),
     colors::ORANGE);
     let mut xp_to_gain = 0;  
-    for (id, obj) in objects.iter_mut().enumerate() {  
+    for (id, obj) in objects.iter_mut() {
         if obj.distance(x, y) <= FIREBALL_RADIUS as f32 && obj.fighter.is_some() {
             game.log.add(format!("The {} gets burned for {} hit points.", obj.name, FIREBALL_DAMAGE),
             colors::ORANGE);
-            if let Some(xp) = obj.take_damage(FIREBALL_DAMAGE, game) {
+            if let Some(xp) = obj.take_damage(FIREBALL_DAMAGE, "fireball", game) {
                 // Don't reward the player for burning themself!
                 if id != PLAYER {  
                     xp_to_gain += xp;
@@ -122,7 +71,8 @@ pub fn cast_fireball(_inventory_id: usize, objects: &mut [Object],game: &mut Gam
     UseResult::UsedUp
 }
 
-pub fn cast_heal(_inventory_id: usize, objects: &mut [Object], game: &mut Game, _tcod: &mut Tcod)
+pub fn cast_heal(_inventory_id: usize, _target: Option<(i32, i32)>, objects: &mut Entities,
+                 game: &mut Game, _fov_map: &FovMap)
              -> UseResult
 {
     // heal the player
@@ -138,17 +88,18 @@ pub fn cast_heal(_inventory_id: usize, objects: &mut [Object], game: &mut Game,
     }
     UseResult::Cancelled
 }
-pub fn cast_lightning(_inventory_id: usize, objects: &mut [Object], game: &mut Game, tcod: &mut Tcod) -> UseResult
+pub fn cast_lightning(_inventory_id: usize, _target: Option<(i32, i32)>, objects: &mut Entities,
+                      game: &mut Game, fov_map: &FovMap) -> UseResult
 {
     // find closest enemy (inside a maximum range) and damage it
-    let monster_id = closest_monster(LIGHTNING_RANGE, objects, tcod);
+    let monster_id = closest_monster(LIGHTNING_RANGE, objects, fov_map);
     if let Some(monster_id) = monster_id {
         // zap it!
         game.log.add(format!("A lightning bolt strikes the {} with a loud thunder! \
                         The damage is {} hit points.",
                         objects[monster_id].name, LIGHTNING_DAMAGE),
                         colors::LIGHT_BLUE);
-        objects[monster_id].take_damage(LIGHTNING_DAMAGE, game);
+        objects[monster_id].take_damage(LIGHTNING_DAMAGE, "lightning", game);
 
         UseResult::UsedUp
     } else {  // no enemy found within maximum range
@@ -157,12 +108,12 @@ pub fn cast_lightning(_inventory_id: usize, objects: &mut [Object], game: &mut G
     }
 }
 
-pub fn cast_confuse(_inventory_id: usize, objects: &mut [Object], game: &mut Game,tcod: &mut Tcod)
+pub fn cast_confuse(_inventory_id: usize, target: Option<(i32, i32)>, objects: &mut Entities,
+                    game: &mut Game, fov_map: &FovMap)
     -> UseResult
 {
-    // ask the player for a target to confuse
-    game.log.add("Left-click an enemy to confuse it, or right-click to cancel.", colors::LIGHT_CYAN);
-    let monster_id = target_monster(tcod, objects, game, Some(CONFUSE_RANGE as f32));
+    // the target has to be a visible monster in range
+    let monster_id = target.and_then(|(x, y)| monster_at(x, y, Some(CONFUSE_RANGE as f32), objects, fov_map));
     if let Some(monster_id) = monster_id {
         let old_ai = objects[monster_id].ai.take().unwrap_or(Ai::Basic);
         // replace the monster's AI with a "confused" one; after
@@ -183,8 +134,7 @@ pub fn cast_confuse(_inventory_id: usize, objects: &mut [Object], game: &mut Gam
 
 pub fn player_death(player: &mut Object, messages: &mut Messages) {
     // the game ended!
-    // TODO Replace with game.log.add()
-    message(messages, "You died!", colors::DARK_RED);
+    messages.add("You died!", colors::DARK_RED);
 
     // for added effect, transform the player into a corpse!
     player.char = CORPSE;
@@ -194,10 +144,8 @@ pub fn player_death(player: &mut Object, messages: &mut Messages) {
 pub fn monster_death(monster: &mut Object, messages: &mut Messages) {
     // transform it into a nasty corpse! it doesn't block, can't be
     // attacked and doesn't move
-    // TODO Replace with game.log.add()
-    // message(messages, format!("{} is dead!", monster.name), colors::ORANGE);
-    message(messages, format!("{} is dead! You gain {} experience points.",
-                              monster.name, monster.fighter.unwrap().xp), colors::ORANGE);
+    messages.add(format!("{} is dead! You gain {} experience points.",
+                         monster.name, monster.fighter.unwrap().xp), colors::ORANGE);
     monster.char = CORPSE;
     monster.color = colors::DARK_RED;
     monster.blocks = false;
@@ -207,22 +155,26 @@ pub fn monster_death(monster: &mut Object, messages: &mut Messages) {
 }
 
 
-pub fn player_move_or_attack(dx: i32, dy: i32, objects: &mut [Object], game: &mut Game) {
+pub fn player_move_or_attack(dx: i32, dy: i32, objects: &mut Entities, game: &mut Game, fov: &mut FovMap) {
     // the coordinates the player is moving to/attacking
     let x = objects[PLAYER].x + dx;
     let y = objects[PLAYER].y + dy;
 
     // try to find an attackable object there
-    let target_id = objects.iter().position(|object| {
-        object.fighter.is_some() && object.pos() == (x, y)
-    });
+    let target_id = objects.with_fighter()
+        .find(|&(_, object)| object.pos() == (x, y))
+        .map(|(id, _)| id);
 
     // attack if target found, move otherwise
     match target_id {
         Some(target_id) => {
-            let (player, target) = mut_two(PLAYER, target_id, objects);
-            player.attack(target, game);
-
+            if let Some((player, target)) = objects.get_two_mut(PLAYER, target_id) {
+                player.attack(target, game);
+            }
+        }
+        // walking into a closed door opens it
+        None if game.map[x as usize][y as usize].door.map_or(false, |door| door != Door::Open) => {
+            player_open_door(x, y, game, fov);
         }
         None => {
             move_by(PLAYER, dx, dy, &mut game.map, objects);
@@ -230,64 +182,128 @@ pub fn player_move_or_attack(dx: i32, dy: i32, objects: &mut [Object], game: &mu
     }
 }
 
-pub fn ai_take_turn(monster_id: usize, objects: &mut [Object], game: &mut Game, fov_map: &FovMap) {
+pub fn ai_take_turn(monster_id: EntityId, objects: &mut Entities, game: &mut Game, fov_map: &mut FovMap) {
     use Ai::*;
     if let Some(ai) = objects[monster_id].ai.take() {
         let new_ai = match ai {
             Basic => ai_basic(monster_id, game, objects, fov_map),
+            Alerted => ai_alerted(monster_id, game, objects, fov_map),
             Confused{previous_ai, num_turns} => ai_confused(
-                monster_id, &mut game.map, objects, &mut game.log, previous_ai, num_turns)
+                monster_id, &mut game.map, objects, &mut game.log, &mut game.rng, previous_ai, num_turns)
         };
         objects[monster_id].ai = Some(new_ai);
     }
 }
 
-pub fn ai_basic(monster_id: usize, game: &mut Game, objects: &mut [Object], fov_map: &FovMap) -> Ai {
+pub fn ai_basic(monster_id: EntityId, game: &mut Game, objects: &mut Entities, fov_map: &mut FovMap) -> Ai {
     // a basic monster takes its turn. If you can see it, it can see you
     let (monster_x, monster_y) = objects[monster_id].pos();
     if fov_map.is_in_fov(monster_x, monster_y) {
         if objects[monster_id].distance_to(&objects[PLAYER]) >= 2.0 {
-            // move towards player if far away
-            let (player_x, player_y) = objects[PLAYER].pos();
-            move_towards(monster_id, player_x, player_y, &mut game.map, objects);
+            // move towards player if far away, opening any door in the way
+            let (dx, dy) = step_towards(objects[monster_id].pos(), objects[PLAYER].pos());
+            let (next_x, next_y) = (monster_x + dx, monster_y + dy);
+            let next_tile = game.map[next_x as usize][next_y as usize];
+            if next_tile.door == Some(Door::Closed) {
+                set_door(next_x, next_y, Door::Open, &mut game.map, fov_map);
+                game.log.add(format!("The {} opens a door.", objects[monster_id].name), colors::LIGHT_GREY);
+            } else if next_tile.kind.is_safe() {
+                // monsters know better than to walk into lava or off a ledge
+                move_by(monster_id, dx, dy, &mut game.map, objects);
+            }
         } else if objects[PLAYER].fighter.map_or(false, |f| f.hp > 0) {
             // close enough, attack! (if the player is still alive.)
-            let (monster, player) = mut_two(monster_id, PLAYER, objects);
-            monster.attack(player, game);
+            if let Some((monster, player)) = objects.get_two_mut(monster_id, PLAYER) {
+                monster.attack(player, game);
+            }
         }
     }
     Ai::Basic
 }
 
-pub fn ai_confused(monster_id: usize, map: &Map, objects: &mut [Object], messages: &mut Messages,
-               previous_ai: Box<Ai>, num_turns: i32) -> Ai {
+/// A monster that heard an alarm heads for the player the shortest way round,
+/// and fights like any other once it can see them
+pub fn ai_alerted(monster_id: EntityId, game: &mut Game, objects: &mut Entities, fov_map: &mut FovMap) -> Ai {
+    let (monster_x, monster_y) = objects[monster_id].pos();
+    if fov_map.is_in_fov(monster_x, monster_y) {
+        return ai_basic(monster_id, game, objects, fov_map);
+    }
+    if let Some((dx, dy)) = path_step(&game.map, (monster_x, monster_y), objects[PLAYER].pos()) {
+        let (next_x, next_y) = (monster_x + dx, monster_y + dy);
+        if game.map[next_x as usize][next_y as usize].door == Some(Door::Closed) {
+            set_door(next_x, next_y, Door::Open, &mut game.map, fov_map);
+        } else {
+            move_by(monster_id, dx, dy, &game.map, objects);
+        }
+    }
+    Ai::Alerted
+}
+
+/// The first step on the shortest way from one tile to another that a monster
+/// would take: through doors it knows how to open and round anything that would hurt it
+fn path_step(map: &Map, from: (i32, i32), to: (i32, i32)) -> Option<(i32, i32)> {
+    // search out from the goal, so the tile that reaches `from` is the step to take
+    let mut seen = vec![vec![false; MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+    let mut queue = VecDeque::new();
+    seen[to.0 as usize][to.1 as usize] = true;
+    queue.push_back(to);
+    while let Some((x, y)) = queue.pop_front() {
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let (next_x, next_y) = (x + dx, y + dy);
+                if next_x < 0 || next_y < 0 || next_x >= MAP_WIDTH || next_y >= MAP_HEIGHT {
+                    continue;
+                }
+                if (next_x, next_y) == from {
+                    return Some((x - from.0, y - from.1));
+                }
+                let tile = &map[next_x as usize][next_y as usize];
+                let walkable = tile.passable() && tile.door != Some(Door::Locked) &&
+                    tile.door != Some(Door::Secret);
+                if walkable && !seen[next_x as usize][next_y as usize] {
+                    seen[next_x as usize][next_y as usize] = true;
+                    queue.push_back((next_x, next_y));
+                }
+            }
+        }
+    }
+    None
+}
+
+pub fn ai_confused(monster_id: EntityId, map: &Map, objects: &mut Entities, messages: &mut Messages,
+               rng: &mut GameRng, previous_ai: Box<Ai>, num_turns: i32) -> Ai {
     if num_turns >= 0 {  // still confused ...
         // move in a random idrection, and decrease the number of turns confused
         move_by(monster_id,
-                rand::thread_rng().gen_range(-1, 2),
-                rand::thread_rng().gen_range(-1, 2),
+                rng.gen_range(-1, 2),
+                rng.gen_range(-1, 2),
                 map,
                 objects);
         Ai::Confused{previous_ai: previous_ai, num_turns: num_turns - 1}
     } else {  // restore the previous AI (this one will be deleted)
-        // TODO Replace with game.log.add()
-        message(messages, format!("The {} is no longer confused!",
-                                  objects[monster_id].name),
-                                  colors::RED);
+        messages.add(format!("The {} is no longer confused!",
+                             objects[monster_id].name),
+                             colors::RED);
         *previous_ai
     }
 }
-pub fn move_towards(id: usize, target_x: i32, target_y: i32, map: &Map, objects: &mut [Object]) {
+pub fn move_towards(id: EntityId, target_x: i32, target_y: i32, map: &Map, objects: &mut Entities) {
+    let (dx, dy) = step_towards(objects[id].pos(), (target_x, target_y));
+    move_by(id, dx, dy, map, objects);
+}
+
+/// the single step, diagonals included, that heads most directly from one tile to another
+pub fn step_towards(from: (i32, i32), to: (i32, i32)) -> (i32, i32) {
     // vector from this object to the target, and distance
-    let dx = target_x - objects[id].x;
-    let dy = target_y - objects[id].y;
+    let dx = to.0 - from.0;
+    let dy = to.1 - from.1;
     let distance = ((dx.pow(2) + dy.pow(2)) as f32).sqrt();
 
     // normalize it to length 1 (preserving direction), then round it and
     // convert to integer so the movement is restricted to the map grid
     let dx = (dx as f32 / distance).round() as i32;
     let dy = (dy as f32 / distance).round() as i32;
-    move_by(id, dx, dy, map, objects);
+    (dx, dy)
 }
 
 
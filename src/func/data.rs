@@ -0,0 +1,78 @@
+use std::io::Read;
+use std::fs::{self, File};
+use std::error::Error;
+
+use serde::de::DeserializeOwned;
+
+use crate::types::*;
+use crate::r#const::*;
+
+/// Load every template the dungeon is generated from
+pub fn load_templates() -> Result<Templates, Box<dyn Error>> {
+    let monsters: Vec<MonsterTemplate> = load_data_file(MONSTERS_FILE)?;
+    let items: Vec<ItemTemplate> = load_data_file(ITEMS_FILE)?;
+    let prefabs = load_prefabs(&monsters, &items)?;
+    Ok(Templates {
+        monsters,
+        items,
+        prefabs,
+    })
+}
+
+/// Read every `.txt` file in `PREFABS_DIR`, checking that the monsters and
+/// items they use exist. No directory just means no prefabs.
+pub fn load_prefabs(monsters: &[MonsterTemplate], items: &[ItemTemplate])
+    -> Result<Vec<Prefab>, Box<dyn Error>> {
+    let entries = match fs::read_dir(PREFABS_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(vec![]),
+    };
+    let mut paths = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().map_or(false, |extension| extension == "txt") {
+            paths.push(path);
+        }
+    }
+    // the same order everywhere, so the same seed gives the same dungeon
+    paths.sort();
+
+    let mut prefabs = vec![];
+    for path in paths {
+        let mut text = String::new();
+        File::open(&path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+        let prefab = Prefab::parse(&text)
+            .map_err(|e| format!("Couldn't parse {}: {}", path.display(), e))?;
+        let unknown_monster = prefab.monsters.values()
+            .find(|&name| !monsters.iter().any(|monster| &monster.name == name));
+        let unknown_item = prefab.items.values()
+            .find(|&name| !items.iter().any(|item| &item.name == name));
+        if let Some(name) = unknown_monster.or(unknown_item) {
+            return Err(format!("{} uses \"{}\", which isn't in the game data", path.display(), name).into());
+        }
+        prefabs.push(prefab);
+    }
+    Ok(prefabs)
+}
+
+/// The fonts that can be picked in the settings, the first being the default
+pub fn load_fonts() -> Result<Vec<FontInfo>, Box<dyn Error>> {
+    let fonts: Vec<FontInfo> = load_data_file(FONTS_FILE)?;
+    if fonts.is_empty() {
+        return Err(format!("{} doesn't list any fonts", FONTS_FILE).into());
+    }
+    Ok(fonts)
+}
+
+/// Read a JSON data file, naming the file in any error
+pub fn load_data_file<T: DeserializeOwned>(path: &str) -> Result<T, Box<dyn Error>> {
+    let mut json = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut json))
+        .map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+    let data = serde_json::from_str(&json)
+        .map_err(|e| format!("Couldn't parse {}: {}", path, e))?;
+    Ok(data)
+}
@@ -0,0 +1,89 @@
+use crate::types::colors;
+
+use crate::types::*;
+use crate::r#const::*;
+
+/// Change the door at (x, y) and update the FOV map to match, since whether
+/// it's open changes what can be seen through it
+pub fn set_door(x: i32, y: i32, door: Door, map: &mut Map, fov: &mut FovMap) {
+    let tile = &mut map[x as usize][y as usize];
+    *tile = Tile { explored: tile.explored, ..Tile::door(door) };
+    fov.set(x, y, !tile.block_sight);
+}
+
+/// The player walks into a closed door: open it, or unlock it if it's locked
+/// and they have a key. Returns whether the door opened.
+pub fn player_open_door(x: i32, y: i32, game: &mut Game, fov: &mut FovMap) -> bool {
+    match game.map[x as usize][y as usize].door {
+        Some(Door::Closed) => {
+            set_door(x, y, Door::Open, &mut game.map, fov);
+            game.log.add("You open the door.", colors::LIGHT_GREY);
+            true
+        }
+        Some(Door::Locked) => {
+            let key = game.inventory.iter().position(|item| item.item == Some(Item::Key));
+            match key {
+                Some(key) => {
+                    // the key stays in the lock
+                    game.inventory.remove(key);
+                    set_door(x, y, Door::Open, &mut game.map, fov);
+                    game.log.add("You unlock the door with your key.", colors::LIGHT_GREY);
+                    true
+                }
+                None => {
+                    game.log.add("The door is locked. You'll need a key.", colors::LIGHT_GREY);
+                    false
+                }
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Close the open door next to the player, in the direction (dx, dy).
+/// Returns whether it took a turn.
+pub fn close_door(dx: i32, dy: i32, objects: &Entities, game: &mut Game, fov: &mut FovMap) -> bool {
+    let (x, y) = (objects[PLAYER].x + dx, objects[PLAYER].y + dy);
+    if game.map[x as usize][y as usize].door != Some(Door::Open) {
+        game.log.add("There's no open door there.", colors::LIGHT_GREY);
+        return false;
+    }
+    // anything at all in the doorway, even an item, stops it shutting
+    if objects.iter().any(|(_, object)| object.pos() == (x, y)) {
+        game.log.add("Something's in the way.", colors::LIGHT_GREY);
+        return false;
+    }
+    set_door(x, y, Door::Closed, &mut game.map, fov);
+    game.log.add("You close the door.", colors::LIGHT_GREY);
+    true
+}
+
+/// Close the open door next to the player, if there's only the one.
+/// Returns whether it took a turn.
+pub fn close_nearby_door(objects: &Entities, game: &mut Game, fov: &mut FovMap) -> bool {
+    match open_doors_around(objects, &game.map)[..] {
+        [(dx, dy)] => close_door(dx, dy, objects, game, fov),
+        [] => {
+            game.log.add("There's no open door next to you.", colors::LIGHT_GREY);
+            false
+        }
+        _ => {
+            game.log.add("There's more than one open door here. Which one?", colors::LIGHT_GREY);
+            false
+        }
+    }
+}
+
+/// the directions of the open doors next to the player
+pub fn open_doors_around(objects: &Entities, map: &Map) -> Vec<(i32, i32)> {
+    let (x, y) = objects[PLAYER].pos();
+    let mut doors = vec![];
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            if map[(x + dx) as usize][(y + dy) as usize].door == Some(Door::Open) {
+                doors.push((dx, dy));
+            }
+        }
+    }
+    doors
+}
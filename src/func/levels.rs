@@ -1,5 +1,9 @@
+use std::mem;
+
+use rand::Rng;
+
 use super::*;
-use tcod::colors::{self, Color};
+use crate::types::colors;
 use crate::r#const::*;
 use crate::types::*;
 
@@ -12,9 +16,14 @@ pub fn from_dungeon_level(table: &[Transition], level: u32) -> u32 {
         .map_or(0, |transition| transition.value)
 }
 
-pub fn level_up(objects: &mut [Object], game: &mut Game, tcod: &mut Tcod) {
+/// Experience the player needs to reach their next level
+pub fn level_up_xp(player: &Object) -> i32 {
+    LEVEL_UP_BASE + player.level * LEVEL_UP_FACTOR
+}
+
+pub fn level_up(stat: Stat, objects: &mut Entities, game: &mut Game) {
     let player = &mut objects[PLAYER];
-    let level_up_xp = LEVEL_UP_BASE + player.level * LEVEL_UP_FACTOR;
+    let level_up_xp = level_up_xp(player);
     // see if the player's experience is enough to level-up
     if player.fighter.as_ref().map_or(0, |f| f.xp) >= level_up_xp {
         // it is! level up
@@ -23,45 +32,104 @@ pub fn level_up(objects: &mut [Object], game: &mut Game, tcod: &mut Tcod) {
                              player.level),
                              colors::YELLOW);
         let fighter = player.fighter.as_mut().unwrap();
-        let mut choice = None;
-        while choice.is_none() {  // keep asking until a choice is made
-            choice = menu(
-                "Level up! Choose a stat to raise:\n",
-                &[format!("Constitution (+20 HP, from {})", fighter.base_max_hp),
-                format!("Strength (+1 attack, from {})", fighter.base_power),
-                format!("Agility (+1 defense, from {})", fighter.base_defense)],
-                LEVEL_SCREEN_WIDTH, &mut tcod.root);
-        };
         fighter.xp -= level_up_xp;
-        match choice.unwrap() {
-            0 => {
+        match stat {
+            Stat::Constitution => {
                 fighter.base_max_hp += 20;
                 fighter.hp = fighter.base_max_hp;
             }
-            1 => {
+            Stat::Strength => {
                 fighter.base_power += 1;
             }
-            2 => {
+            Stat::Agility => {
                 fighter.base_defense += 1;
             }
-            _ => unreachable!(),
         }
     }
 }
 
-/// Advance to the next level
-pub fn next_level(tcod: &mut Tcod, objects: &mut Vec<Object>, game: &mut Game) {
-    game.log.add("You take a moment to rest, and recover your strength.", colors::VIOLET);
-    let heal_hp = objects[PLAYER].max_hp(game) / 2;
-    objects[PLAYER].heal(heal_hp, game);
+/// Go down the stairs to the next level, making it if the player hasn't been there before
+pub fn next_level(objects: &mut Entities, game: &mut Game, fov: &mut FovMap, templates: &Templates) {
+    let depth = game.dungeon_level + 1;
+    if game.levels.contains_key(&depth) {
+        game.log.add("You descend the stairs again.", colors::RED);
+    } else {
+        // only the first time, or going up and down would be a free rest
+        game.log.add("You take a moment to rest, and recover your strength.", colors::VIOLET);
+        let heal_hp = objects[PLAYER].max_hp(game) / 2;
+        objects[PLAYER].heal(heal_hp, game);
+
+        game.log.add("After a rare moment of peace, you descend deeper into \
+                      the heart of the dungeon...", colors::RED);
+        objects[PLAYER].fighter.as_mut().unwrap().xp += (depth * 10) as i32;
+    }
+    change_level(depth, Some("up stairs"), objects, game, fov, templates);
+}
+
+/// Drop down a chasm to the next level, landing anywhere on it
+pub fn fall_to_next_level(objects: &mut Entities, game: &mut Game, fov: &mut FovMap, templates: &Templates) {
+    let depth = game.dungeon_level + 1;
+    if !game.levels.contains_key(&depth) {
+        objects[PLAYER].fighter.as_mut().unwrap().xp += (depth * 10) as i32;
+    }
+    change_level(depth, None, objects, game, fov, templates);
+}
 
-    game.log.add("After a rare moment of peace, you descend deeper into \
-                  the heart of the dungeon...", colors::RED);
-    game.dungeon_level += 1;
+/// Go back up the stairs to the level above, just as it was left
+pub fn previous_level(objects: &mut Entities, game: &mut Game, fov: &mut FovMap, templates: &Templates) {
+    game.log.add("You climb back up the stairs.", colors::VIOLET);
+    let depth = game.dungeon_level - 1;
+    change_level(depth, Some("stairs"), objects, game, fov, templates);
+}
 
-    objects[PLAYER].fighter.as_mut().unwrap().xp += (game.dungeon_level * 10) as i32;
+/// Put the current level away in `game.levels` and move the player to
+/// `depth`, onto the stairs called `arrive_on`, or anywhere they can walk
+/// from there if None. Levels that haven't been visited yet are generated.
+fn change_level(depth: u32, arrive_on: Option<&str>, objects: &mut Entities, game: &mut Game,
+                fov: &mut FovMap, templates: &Templates) {
+    let left_behind = objects.ids().into_iter()
+        .filter(|&id| id != PLAYER)
+        .filter_map(|id| objects.despawn(id))
+        .collect();
+    let map = mem::take(&mut game.map);
+    game.levels.insert(game.dungeon_level, Level { map, objects: left_behind });
 
-    game.map = make_map(objects, game.dungeon_level);
-    initialise_fov(&game.map, tcod);
+    game.dungeon_level = depth;
+    game.map = match game.levels.remove(&depth) {
+        Some(level) => {
+            for object in level.objects {
+                objects.spawn(object);
+            }
+            level.map
+        }
+        None => make_map(objects, depth, &mut game.rng, templates),
+    };
 
+    match arrive_on {
+        Some(stairs) => {
+            let stairs = objects.iter()
+                .find(|&(_, object)| object.name == stairs)
+                .map(|(_, object)| object.pos());
+            if let Some((x, y)) = stairs {
+                objects[PLAYER].set_pos(x, y);
+            }
+        }
+        None => {
+            // somewhere on plain floor that can be walked to from the up stairs
+            let start = objects.iter()
+                .find(|&(_, object)| object.name == "up stairs")
+                .map_or(objects[PLAYER].pos(), |(_, object)| object.pos());
+            let landing: Vec<_> = connected_region(&game.map, start, Tile::passable).into_iter()
+                .filter(|&(x, y)| {
+                    let tile = &game.map[x as usize][y as usize];
+                    tile.kind == TileKind::Floor && tile.door.is_none() && tile.trap.is_none() &&
+                        !is_blocked(x, y, &game.map, objects)
+                })
+                .collect();
+            if let Some(&(x, y)) = game.rng.choose(&landing) {
+                objects[PLAYER].set_pos(x, y);
+            }
+        }
+    }
+    initialise_fov(&game.map, fov);
 }
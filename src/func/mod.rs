@@ -1,19 +1,14 @@
-use std::io::{Read, Write};
-use std::fs::File;
-use std::error::Error;
+use std::fs;
 
 use rand::Rng;
 use rand::distributions::{Weighted, WeightedChoice, IndependentSample};
 
 use std::cmp;
+use std::collections::BTreeMap;
 
-use tcod::console::*;
-use tcod::colors::{self, Color};
-use tcod::map::{Map as FovMap, FovAlgorithm};
+use crate::types::colors;
 
-use tcod::input::Key;
-use tcod::input::{self, Event, Mouse};
-use tcod::input::KeyCode::*;
+use crate::types::KeyCode::*;
 
 use crate::types::*;
 use crate::r#const::*;
@@ -22,214 +17,279 @@ pub mod levels;
 pub mod combat;
 pub mod items;
 pub mod ui;
+pub mod data;
+pub mod replay;
+pub mod save;
+pub mod settings;
+pub mod bot;
+pub mod prefabs;
+pub mod reachability;
+pub mod doors;
+pub mod terrain;
+pub mod traps;
+pub mod secrets;
 
 pub use combat::*;
 pub use ui::*;
 pub use items::*;
 pub use levels::*;
-
-// Handle keydown events here
-pub fn handle_keys(key: Key, tcod: &mut Tcod, objects: &mut Vec<Object>,
-                   game: &mut Game) -> PlayerAction {
+pub use data::*;
+pub use replay::*;
+pub use save::*;
+pub use settings::*;
+pub use bot::*;
+pub use prefabs::*;
+pub use reachability::*;
+pub use doors::*;
+pub use terrain::*;
+pub use traps::*;
+pub use secrets::*;
+
+// Handle keydown events here, looking up what each key does in the keymap.
+// Along with the action comes whatever the game reported back, if it was sent a command.
+pub fn handle_keys(key: Key, renderer: &mut dyn Renderer, state: &mut GameState,
+                   keymap: &Keymap) -> (PlayerAction, Vec<GameEvent>) {
     use PlayerAction::*;
-    // todo: handle keys
-
-    let player_alive = objects[PLAYER].alive;
-    match (key, player_alive) {
-        (Key { code: Enter, ctrl: true, .. }, _)=> {
-            // Alt+Enter: toggle fullscreen
-            let fullscreen = tcod.root.is_fullscreen();
-            tcod.root.set_fullscreen(!fullscreen);
-            DidntTakeTurn
+
+    let action = match keymap.action_for(key) {
+        Some(action) => action,
+        None => return (DidntTakeTurn, vec![]),
+    };
+    let player_alive = state.objects[PLAYER].alive;
+    let command = match (action, player_alive) {
+        (Action::Fullscreen, _) => {
+            renderer.toggle_fullscreen();
+            return (DidntTakeTurn, vec![])
         }
-        (Key { code: Escape, .. }, _) => Exit,  // exit game
+        (Action::Quit, _) => return (Exit, vec![]),  // exit game
         // movement keys
-        (Key { code: Up, .. }, true) | (Key { code: NumPad8, ..}, true) => {
-            player_move_or_attack(0, -1, objects, game);
-            TookTurn
-        }
-        (Key { code: Down, .. }, true) | (Key { code: NumPad2, ..}, true) => {
-            player_move_or_attack(0, 1, objects, game);
-            TookTurn
-        }
-        (Key { code: Left, .. }, true) | (Key { code: NumPad4, ..}, true) => {
-            player_move_or_attack(-1, 0, objects, game);
-            TookTurn
-        }
-        (Key { code: Right, .. }, true) | (Key { code: NumPad6, ..}, true) => {
-            player_move_or_attack(1, 0, objects, game);
-            TookTurn
-        }
-        (Key { code: Home, .. }, true) | (Key { code: NumPad7, ..}, true) => {
-            player_move_or_attack(-1, -1, objects, game);
-            TookTurn
-        }
-        (Key { code: PageUp, .. }, true) | (Key { code: NumPad9, ..}, true) => {
-            player_move_or_attack(1, -1, objects, game);
-            TookTurn
+        (action, true) if action.direction().is_some() => {
+            let (dx, dy) = action.direction().unwrap();
+            Command::Move { dx, dy }
         }
-        (Key { code: End, .. }, true) | (Key { code: NumPad1, ..}, true) => {
-            player_move_or_attack(-1, 1, objects, game);
-            TookTurn
-        }
-        (Key { code: PageDown, .. }, true) | (Key { code: NumPad3, ..}, true) => {
-            player_move_or_attack(1, 1, objects, game);
-            TookTurn
-        }
-        (Key { code: NumPad5, .. }, true) => {
-            TookTurn  // do nothing, i.e. wait for the monster to come to you
-        }
-        (Key { printable: 'g', .. }, true) => {
-            // pick up an item
-            let item_id = objects.iter().position(|object| {
-                object.pos() == objects[PLAYER].pos() && object.item.is_some()
-            });
-            if let Some(item_id) = item_id {
-                pick_item_up(item_id, objects, &mut game.inventory, &mut game.log);
-            }
-            DidntTakeTurn
-        },
-        (Key { printable: 'i', .. }, true) => {
+        (Action::Wait, true) => Command::Wait,
+        (Action::PickUp, true) => Command::PickUp,
+        (Action::Inventory, true) => {
             // show the inventory: if an item is selected, use it
             let inventory_index = inventory_menu(
-                &mut game.inventory,
+                &mut state.game.inventory,
                 "Press the key next to an item to use it, or any other to cancel.\n",
-                &mut tcod.root);
-            if let Some(inventory_index) = inventory_index {
-                use_item(inventory_index, objects, game, tcod);
+                renderer);
+            match inventory_index {
+                Some(inventory_id) => {
+                    let target = target_for_item(inventory_id, renderer, state, keymap);
+                    Command::UseItem { inventory_id, target }
+                }
+                None => return (DidntTakeTurn, vec![]),
             }
-            DidntTakeTurn
         },
-        (Key { printable: 'd', .. }, true) => {
+        (Action::Drop, true) => {
             // show the inventory; if an item is selected, drop it
             let inventory_index = inventory_menu(
-                &mut game.inventory,
+                &mut state.game.inventory,
                 "Press the key next to an item to drop it, or any other to cancel.\n'",
-                &mut tcod.root);
-            if let Some(inventory_index) = inventory_index {
-                drop_item(inventory_index, &mut game.inventory, objects, &mut game.log);
+                renderer);
+            match inventory_index {
+                Some(inventory_id) => Command::DropItem { inventory_id },
+                None => return (DidntTakeTurn, vec![]),
             }
-            DidntTakeTurn
         },
-        (Key { printable: '<', .. }, true) => {
-            // go down stairs, if the player is on them
-            let player_on_stairs = objects.iter().any(|object| {
-                object.pos() == objects[PLAYER].pos() && object.name == "stairs"
-            });
-            if player_on_stairs {
-                next_level(tcod, objects, game);
+        (Action::Descend, true) => Command::Descend,
+        (Action::Ascend, true) => Command::Ascend,
+        (Action::CloseDoor, true) => {
+            // only ask which door if there's more than one to pick from
+            if open_doors_around(&state.objects, &state.game.map).len() > 1 {
+                match ask_direction("Close the door in which direction?", renderer, keymap) {
+                    Some((dx, dy)) => Command::CloseDoor { dx, dy },
+                    None => return (DidntTakeTurn, vec![]),
+                }
+            } else {
+                Command::CloseNearbyDoor
             }
-            DidntTakeTurn
-        },
-        (Key { printable: 'c', .. }, true) => {
+        }
+        (Action::Search, true) => Command::Search,
+        (Action::CharacterSheet, true) => {
             // show character information
-            let player = &objects[PLAYER];
+            let player = &state.objects[PLAYER];
+            let game = &state.game;
             let level = player.level;
-            let level_up_xp = LEVEL_UP_BASE + player.level * LEVEL_UP_FACTOR;
+            let level_up_xp = level_up_xp(player);
             if let Some(fighter) = player.fighter.as_ref() {
                 let msg = format!("Character information
 
+Name: {}
 Level: {}
 Experience: {}
 Experience to level up: {}
 
 Maximum HP: {}
 Attack: {}
-Defense: {}", level, fighter.xp, level_up_xp, player.max_hp(game), player.power(game), player.defense(game));
-         msgbox(&msg, CHARACTER_SCREEN_WIDTH, &mut tcod.root);
+Defense: {}
+
+Seed: {}", game.name, level, fighter.xp, level_up_xp, player.max_hp(game), player.power(game), player.defense(game),
+                    game.seed.map_or("unknown".to_string(), |seed| seed.to_string()));
+         msgbox(&msg, CHARACTER_SCREEN_WIDTH, renderer);
             }
 
-            DidntTakeTurn
+            return (DidntTakeTurn, vec![])
         }
-        _ => DidntTakeTurn,
+        _ => return (DidntTakeTurn, vec![]),
+    };
+
+    let events = state.step(command);
+    if events.contains(&GameEvent::TurnTaken) {
+        (TookTurn, events)
+    } else {
+        (DidntTakeTurn, events)
     }
+}
 
+pub fn make_map(objects: &mut Entities, level: u32, rng: &mut GameRng, templates: &Templates) -> Map {
+    generate_map(objects, level, rng, templates, |rng| generator_for_level(level, rng))
 }
 
-pub fn make_map(objects: &mut Vec<Object>, level: u32) -> Map {
-    // fill map with "blocked" tiles
-    let mut map = vec![vec![Tile::wall(); MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+/// Like `make_map`, but laid out by one kind of generator instead of one
+/// picked for the level (still falling back to rooms if it keeps failing)
+pub fn make_map_with(kind: GeneratorKind, objects: &mut Entities, level: u32, rng: &mut GameRng,
+                     templates: &Templates) -> Map {
+    generate_map(objects, level, rng, templates, |_| kind.generator())
+}
 
-    // Player is the first element, remove everything else.
-    // NOTE: works only when the player is the first object!
-    assert_eq!(&objects[PLAYER] as *const _, &objects[0] as *const _);
-    objects.truncate(1);
+/// Keep building levels with generators from `pick_generator` until one
+/// comes out usable
+fn generate_map<F>(objects: &mut Entities, level: u32, rng: &mut GameRng, templates: &Templates,
+                   mut pick_generator: F) -> Map
+    where F: FnMut(&mut GameRng) -> Box<dyn MapGenerator>
+{
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        // if levels keep coming out wrong, fall back to plain rooms, which always has some
+        let generator = if attempts < MAP_ATTEMPTS {
+            pick_generator(rng)
+        } else {
+            GeneratorKind::Rooms.generator()
+        };
+        if let Some(map) = build_level(&*generator, objects, level, rng, templates) {
+            return map;
+        }
+    }
+}
 
-    let mut rooms = vec![];
+/// Lay out a level with `generator` and fill it. Returns None if it can't be
+/// used: it has nowhere to put the player, or the stairs or an item can't be
+/// reached and couldn't be tunnelled to.
+fn build_level(generator: &dyn MapGenerator, objects: &mut Entities, level: u32, rng: &mut GameRng,
+               templates: &Templates) -> Option<Map> {
+    // Keep the player, remove everything else.
+    objects.retain(|id, _| id == PLAYER);
+
+    let Layout { mut map, areas } = generator.generate(rng);
+    if areas.is_empty() {
+        return None;
+    }
 
-    for _ in 0..MAX_ROOMS {
-        // random width and height
-        let w = rand::thread_rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
-        let h = rand::thread_rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
-        // random position without going out of the boundaries of the map
-        let x = rand::thread_rng().gen_range(0, MAP_WIDTH - w);
-        let y = rand::thread_rng().gen_range(0, MAP_HEIGHT - h);
+    // hand-drawn rooms go into the rock between areas, with their own monsters and items
+    let prefab_stairs = place_prefabs(&mut map, objects, level, rng, templates);
 
-        let new_room = Rect::new(x, y, w, h);
+    // and maybe a treasure room, behind a secret door
+    place_secret_room(&mut map, objects, level, rng, templates);
 
-        // run through the other rooms and see if they intersect with this one
-        let failed = rooms.iter().any(|other_room| new_room.intersects_with(other_room));
+    // some areas get water, lava, chasms or rubble
+    for area in &areas {
+        place_terrain(area, &mut map, level, rng);
+    }
 
-        if !failed {
-            // this means there are no intersections, so this room is valid
+    // and some have traps hidden in the floor
+    for area in &areas {
+        place_traps(area, &mut map, level, rng);
+    }
 
-            // "paint" it to the map's tiles
-            create_room(new_room, &mut map);
+    // the player starts in the middle of the first area, where the stairs up
+    // are on every level but the first
+    let (start_x, start_y) = areas[0].center();
+    objects[PLAYER].set_pos(start_x, start_y);
+    map[start_x as usize][start_y as usize] = Tile::empty();
+    if level > 1 {
+        let mut up_stairs = Object::new(start_x, start_y, '>', "up stairs", colors::WHITE, false);
+        up_stairs.always_visible = true;
+        objects.spawn(up_stairs);
+    }
 
-            // add some content to this room, such as monsters
-            place_objects(new_room, objects, &mut map, level as u32);
+    // add some content to each area, such as monsters
+    for area in &areas {
+        place_objects(area, objects, &map, level, rng, templates);
+    }
 
-            // center coordinates of the new room, will be useful later
-            let (new_x, new_y) = new_room.center();
+    // create stairs at the center of the last area, unless a prefab has them. If
+    // there's only the one area, the player starts there, so they go as far away as they can
+    let (stairs_x, stairs_y) = prefab_stairs.unwrap_or_else(|| if areas.len() == 1 {
+        areas[0].farthest_from((start_x, start_y))
+    } else {
+        areas[areas.len() - 1].center()
+    });
+    map[stairs_x as usize][stairs_y as usize] = Tile::empty();
+    let mut stairs = Object::new(stairs_x, stairs_y, '<', "stairs", colors::WHITE, false);
+    stairs.always_visible = true;
+    objects.spawn(stairs);
 
-            if rooms.is_empty() {
-                // this is the first room, where the player starts at
-                objects[PLAYER].set_pos(new_x, new_y);
-            } else {
-                // all rooms after the first:
-                // connect it to the previous room with a tunnel
-
-                // center coordinates of the previous room
-                let (prev_x, prev_y) = rooms[rooms.len() - 1].center();
-
-                // toss a coin (random bool value -- either true or false)
-                if rand::random() {
-                    // first move horizontally, then vertically
-                    create_h_tunnel(prev_x, new_x, prev_y, &mut map);
-                    create_v_tunnel(prev_y, new_y, new_x, &mut map);
-                } else {
-                    // first move vertically, then horizontally
-                    create_v_tunnel(prev_y, new_y, prev_x, &mut map);
-                    create_h_tunnel(prev_x, new_x, new_y, &mut map);
-                }
-            }
+    // make sure the stairs and everything worth picking up can be got to
+    if !repair_reachability(&mut map, objects) {
+        return None;
+    }
+    reveal_way_to_stairs(&mut map, objects);
+    place_keys(&mut map, objects, rng, templates);
+    Some(map)
+}
 
-            // finally, append the new room to the list
-            rooms.push(new_room);
+/// Leave a key for every locked door somewhere the player can get to without
+/// going through a locked or secret door. Without a key to use, the doors are just closed.
+fn place_keys(map: &mut Map, objects: &mut Entities, rng: &mut GameRng, templates: &Templates) {
+    let locked_doors: Vec<_> = (0..MAP_WIDTH)
+        .flat_map(|x| (0..MAP_HEIGHT).map(move |y| (x, y)))
+        .filter(|&(x, y)| map[x as usize][y as usize].door == Some(Door::Locked))
+        .collect();
+    let key = match templates.items.iter().find(|item| item.name == KEY_ITEM) {
+        Some(key) => key,
+        None => {
+            for (x, y) in locked_doors {
+                map[x as usize][y as usize] = Tile::door(Door::Closed);
+            }
+            return;
         }
+    };
+    let start = objects[PLAYER].pos();
+    let floor: Vec<_> = connected_region(map, start, |tile| {
+        tile.door != Some(Door::Locked) && tile.door != Some(Door::Secret) && tile.passable()
+    })
+        .into_iter()
+        .filter(|&(x, y)| map[x as usize][y as usize].door.is_none() &&
+                map[x as usize][y as usize].kind == TileKind::Floor)
+        .collect();
+    for _ in &locked_doors {
+        let (x, y) = *rng.choose(&floor).unwrap();
+        objects.spawn(key.spawn(x, y));
     }
-    // create stairs at the center of the last room
-    let (last_room_x, last_room_y) = rooms[rooms.len() - 1].center();
-    let mut stairs = Object::new(last_room_x, last_room_y, '<', "stairs", colors::WHITE, false);
-    stairs.always_visible = true;
-    objects.push(stairs);
-
-    map
 }
 
-/// Returns two muted barrows
-pub fn mut_two<T>(first_index: usize, second_index: usize, items: &mut [T]) -> (&mut T, &mut T) {
-    assert!(first_index != second_index);
-    let split_at_index = cmp::max(first_index, second_index);
-    let (first_slice, second_slice) = items.split_at_mut(split_at_index);
-    if first_index < second_index {
-        (&mut first_slice[first_index], &mut second_slice[0])
-    } else {
-        (&mut second_slice[0], &mut first_slice[second_index])
-    }
+/// Pick how a dungeon level is laid out. The first level is always rooms and
+/// corridors, deeper ones are more and more often BSP, with the odd cave.
+pub fn generator_for_level(level: u32, rng: &mut GameRng) -> Box<dyn MapGenerator> {
+    let generator_chances = &mut [
+        Weighted {weight: from_dungeon_level(&[Transition {level: 1, value: 100}], level),
+                  item: GeneratorKind::Rooms},
+        Weighted {weight: from_dungeon_level(&[Transition {level: 2, value: 35},
+                                               Transition {level: 5, value: 70}], level),
+                  item: GeneratorKind::Bsp},
+        Weighted {weight: from_dungeon_level(&[Transition {level: 3, value: 20},
+                                               Transition {level: 6, value: 30}], level),
+                  item: GeneratorKind::Caves},
+    ];
+    let generator_choice = WeightedChoice::new(generator_chances);
+    generator_choice.ind_sample(rng).generator()
 }
 
-pub fn new_game(tcod: &mut Tcod) -> (Vec<Object>, Game) {
+pub fn new_game(seed: u64, templates: &Templates) -> (Entities, Game) {
     // create object representing the player
     let mut player = Object::new(0, 0, '@', "player", colors::WHITE, true);
     player.alive = true;
@@ -237,30 +297,35 @@ pub fn new_game(tcod: &mut Tcod) -> (Vec<Object>, Game) {
         on_death: DeathCallback::Player, xp: 0});
 
     // the list of objects with just the player
-    let mut objects = vec![player];
+    let mut objects = Entities::new();
+    let player_id = objects.spawn(player);
+    assert_eq!(player_id, PLAYER);
+
+    // everything random in this game comes from here
+    let mut rng = GameRng::new(seed);
 
     let mut game = Game {
         // generate map (at this point it's not drawn to the screen)
-        map: make_map(&mut objects, 1),
+        map: make_map(&mut objects, 1, &mut rng, templates),
         // create the list of game messages and their colors, starts empty
         log: vec![],
         inventory: vec![],
         dungeon_level: 1,
+        seed: Some(seed),
+        rng,
+        history: vec![],
+        name: DEFAULT_NAME.into(),
+        levels: BTreeMap::new(),
     };
 
-    initialise_fov(&game.map, tcod);
-
     // initial equipment: a dagger
-    let mut dagger = Object::new(0, 0, '-', "dagger", colors::SKY, false);
-    dagger.item = Some(Item::Dagger);
-    dagger.equipment = Some(Equipment {
-        equipped: true,
-        slot: Slot::LeftHand,
-        max_hp_bonus: 0,
-        defense_bonus: 0,
-        power_bonus: 2
-    });
-    game.inventory.push(dagger);
+    if let Some(template) = templates.items.iter().find(|item| item.name == STARTING_ITEM) {
+        let mut dagger = template.spawn(0, 0);
+        if let Some(ref mut equipment) = dagger.equipment {
+            equipment.equipped = true;
+        }
+        game.inventory.push(dagger);
+    }
 
     // a warm welcoming message!
     game.log.add("Welcome stranger! Prepare to perish in the Rouge Cachot.",
@@ -269,105 +334,152 @@ pub fn new_game(tcod: &mut Tcod) -> (Vec<Object>, Game) {
     (objects, game)
 }
 
-pub fn initialise_fov(map: &Map, tcod: &mut Tcod) {
+pub fn initialise_fov(map: &Map, fov: &mut FovMap) {
     // create the FOV map, according to the generated map
     for y in 0..MAP_HEIGHT {
         for x in 0..MAP_WIDTH {
-            tcod.fov.set(x, y,
-                         !map[x as usize][y as usize].block_sight,
-                         !map[x as usize][y as usize].blocked);
+            fov.set(x, y, !map[x as usize][y as usize].block_sight);
         }
     }
-    tcod.con.clear();  // unexplored areas start black (which is the default background color)
 }
 
+/// recompute FOV from the player's position and explore every tile in view
+pub fn update_fov(objects: &Entities, game: &mut Game, fov: &mut FovMap) {
+    let player = &objects[PLAYER];
+    fov.compute_fov(player.x, player.y, TORCH_RADIUS, FOV_LIGHT_WALLS);
+
+    for y in 0..MAP_HEIGHT {
+        for x in 0..MAP_WIDTH {
+            if fov.is_in_fov(x, y) {
+                // since it's visible, explore it
+                game.map[x as usize][y as usize].explored = true;
+            }
+        }
+    }
+}
 
-pub fn play_game(objects: &mut Vec<Object>, game: &mut Game, tcod: &mut Tcod) {
-    // force FOV "recompute" first time through the game loop
-    let mut previous_player_position = (-1, -1);
 
-    let mut key = Default::default();
+pub fn play_game(state: &mut GameState, renderer: &mut dyn Renderer, settings: &Settings) {
+    let keymap = settings.keymap();
+    // the tile whose contents are shown in the panel, i.e. under the mouse
+    let mut look = (-1, -1);
 
-    while !tcod.root.window_closed() {
-        match input::check_for_event(input::MOUSE | input::KEY_PRESS) {
-            Some((_, Event::Mouse(m))) => tcod.mouse = m,
-            Some((_, Event::Key(k))) => key = k,
-            _ => key = Default::default(),
+    while !renderer.is_closed() {
+        let mut key = Default::default();
+        match renderer.poll_input() {
+            Some(Input::Mouse(m)) => look = (m.cx, m.cy),
+            Some(Input::Key(k)) => key = k,
+            None => {}
         }
 
         // render the screen
-        let fov_recompute = previous_player_position != (objects[PLAYER].pos());
-        render_all(tcod, &objects, game, fov_recompute);
+        render_all(renderer, state, look);
 
-        tcod.root.flush();
+        renderer.flush();
 
         // level up if needed
-        level_up(objects, game, tcod);
-
-        // erase all objects at their old locations, before they move
-        for object in objects.iter_mut() {
-            object.clear(&mut tcod.con, &mut game.map)
+        if state.level_up_pending() {
+            let stat = level_up_menu(&state.objects[PLAYER], renderer);
+            state.step(Command::LevelUp(stat));
         }
 
         // handle keys and exit game if needed
-        previous_player_position = objects[PLAYER].pos();
-        let player_action = handle_keys(key, tcod, objects, game);
+        let (player_action, events) = handle_keys(key, renderer, state, &keymap);
         if player_action == PlayerAction::Exit {
-            save_game(objects, game);
+            if let Err(e) = save_game(&state.objects, &state.game, settings.save_format) {
+                msgbox(&format!("\nCouldn't save the game: {}\n", e), 50, renderer);
+            }
             break
         }
-
-        // let monstars take their turn
-        if objects[PLAYER].alive && player_action != PlayerAction::DidntTakeTurn {
-            for id in 0..objects.len() {
-                if objects[id].ai.is_some() {
-                    ai_take_turn(id, objects, game, &tcod.fov);
-                }
-            }
+        if events.contains(&GameEvent::SaveDue) {
+            autosave(state, settings);
         }
     }
+    // keep a replay of every game, even if the window was just closed
+    save_replay(state).ok();
 }
 
-pub fn main_menu(tcod: &mut Tcod) {
-    let img = tcod::image::Image::from_file("./img/menu_background.png")  
-        .ok().expect("Background image not found");  
+/// Save without leaving the game, reporting any failure in the message log
+fn autosave(state: &mut GameState, settings: &Settings) {
+    if let Err(e) = save_game(&state.objects, &state.game, settings.save_format) {
+        state.game.log.add(format!("Autosave failed: {}", e), colors::RED);
+    }
+}
 
-    while !tcod.root.window_closed() {  
-        // show the background image, at twice the regular console resolution
-        tcod::image::blit_2x(&img, (0, 0), (-1, -1), &mut tcod.root, (0, 0));
+/// Show the main menu. New games use `seed` if one is given, otherwise a random one.
+pub fn main_menu(renderer: &mut dyn Renderer, templates: &Templates, fonts: &[FontInfo], settings: &mut Settings,
+                 seed: Option<u64>) {
+    match import_legacy_save(settings.save_format) {
+        Ok(Some(LegacyImport::Moved(name))) => {
+            msgbox(&format!("\nYour saved game has been moved to the save slot for {}.\n", name),
+                   50, renderer);
+        }
+        Ok(Some(LegacyImport::SlotTaken(name))) => {
+            msgbox(&format!("\nYour old saved game wasn't moved, as there's already a character called {}. \
+                             It's been kept as {}.imported.\n", name, LEGACY_SAVE_FILE),
+                   50, renderer);
+        }
+        Ok(None) => {}
+        Err(e) => msgbox(&format!("\nCouldn't move your old saved games: {}\n", e), 50, renderer),
+    }
+    while !renderer.is_closed() {  
+        // show the background image, if the renderer can draw one
+        renderer.clear();
+        renderer.draw_image("./img/menu_background.png");
 
-        tcod.root.set_default_foreground(colors::LIGHT_YELLOW);
-        tcod.root.print_ex(SCREEN_WIDTH/2, SCREEN_HEIGHT/2 - 4,
-                           BackgroundFlag::None, TextAlignment::Center,
-                           "ROUGE");
-        tcod.root.print_ex(SCREEN_WIDTH/2, SCREEN_HEIGHT - 2,
-                           BackgroundFlag::None, TextAlignment::Center,
-                           "By Avery Wagar");
+        print_centered(renderer, SCREEN_WIDTH/2, SCREEN_HEIGHT/2 - 4, "ROUGE", colors::LIGHT_YELLOW);
+        print_centered(renderer, SCREEN_WIDTH/2, SCREEN_HEIGHT - 2, "By Avery Wagar", colors::LIGHT_YELLOW);
 
         // show options and wait for the player's choice
-        let choices = &["Play a new game", "Continue last game", "Quit"];
-        let choice = menu("", choices, 24, &mut tcod.root);
-
-
+        let choices = &["Play a new game", "Continue last game", "Delete a character",
+                        "Settings", "Quit"];
+        let choice = menu("", choices, 24, renderer);
 
         match choice {  
             Some(0) => {  // new game
-                let (mut objects, mut game) = new_game(tcod);
-                play_game(&mut objects, &mut game, tcod);
+                let name = match text_input("\nWhat is your name?\n", MAX_NAME_LENGTH, 30, renderer) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                if slot_path(&name).exists() {
+                    msgbox(&format!("\nThere is already a character called {}.\n", name), 40, renderer);
+                    continue;
+                }
+                let mut state = GameState::new(seed.unwrap_or_else(rand::random), templates.clone());
+                state.game.name = name;
+                play_game(&mut state, renderer, settings);
             }
             Some(1) => {  // load game
-                match load_game() {
-                    Ok((mut objects, mut game)) => {
-                        initialise_fov(&game.map, tcod);
-                        play_game(&mut objects, &mut game, tcod);
+                let slot = match choose_save_slot("Continue which character?\n", renderer) {
+                    Some(slot) => slot,
+                    None => continue,
+                };
+                match load_game(&slot.path, templates) {
+                    Ok(mut state) => {
+                        play_game(&mut state, renderer, settings);
                     }
-                    Err(_e) => {
-                        msgbox("\nNo saved game to load.\n", 24, &mut tcod.root);
+                    Err(e) => {
+                        msgbox(&format!("\n{}\n", e), 50, renderer);
                         continue;
                     }
                 }
             }
-            Some(2) => {  // quit
+            Some(2) => {  // delete a save
+                let slot = match choose_save_slot("Delete which character?\n", renderer) {
+                    Some(slot) => slot,
+                    None => continue,
+                };
+                let question = format!("Delete {} for good?\n", slot.name);
+                if menu(&question, &["Yes", "No"], 30, renderer) == Some(0) {
+                    if let Err(e) = fs::remove_file(&slot.path) {
+                        msgbox(&format!("\nCouldn't delete {}: {}\n", slot.name, e), 50, renderer);
+                    }
+                }
+            }
+            Some(3) => {  // settings
+                settings_menu(renderer, fonts, settings);
+            }
+            Some(4) => {  // quit
                 break;
             }
             _ => {}  
@@ -375,21 +487,76 @@ pub fn main_menu(tcod: &mut Tcod) {
     }
 }
 
-pub fn save_game(objects: &[Object], game: &Game) -> Result<(), Box<Error>> {   
-    let save_data = serde_json::to_string(&(objects, game))?;  
-    let mut file = File::create("savegame")?;  
-    file.write_all(save_data.as_bytes())?;  
-    Ok(())  
+/// Change the movement keys and font, saving the settings after each change.
+/// The font can only be changed when the renderer draws with one.
+fn settings_menu(renderer: &mut dyn Renderer, fonts: &[FontInfo], settings: &mut Settings) {
+    loop {
+        let mut choices = vec![format!("Movement keys: {}", settings.movement.name())];
+        if let Some(font) = renderer.font() {
+            choices.push(format!("Font: {}", font.name));
+        }
+        match menu("Settings\n", &choices, 40, renderer) {
+            Some(0) => {
+                let options: Vec<_> = MovementPreset::ALL.iter().map(|preset| preset.name()).collect();
+                match menu("Move with which keys?\n", &options, 40, renderer) {
+                    Some(index) => settings.movement = MovementPreset::ALL[index],
+                    None => continue,
+                }
+            }
+            Some(1) => {
+                let options: Vec<_> = fonts.iter().take(26).map(|font| &font.name[..]).collect();
+                match menu("Use which font?\n", &options, 40, renderer) {
+                    Some(index) => {
+                        renderer.set_font(&fonts[index]);
+                        settings.font = Some(fonts[index].name.clone());
+                    }
+                    None => continue,
+                }
+            }
+            _ => return,
+        }
+        if let Err(e) = save_settings(settings) {
+            msgbox(&format!("\nCouldn't save the settings: {}\n", e), 50, renderer);
+        }
+    }
 }
 
-pub fn load_game() -> Result<(Vec<Object>, Game), Box<Error>> {
-    let mut json_save_state = String::new();
-    let mut file = File::open("savegame")?;
-    file.read_to_string(&mut json_save_state)?;
-    let result = serde_json::from_str::<(Vec<Object>, Game)>(&json_save_state)?;
-    Ok(result)
+/// Let the player pick one of their saves from a menu, a page at a time
+fn choose_save_slot(header: &str, renderer: &mut dyn Renderer) -> Option<SaveSlot> {
+    let mut slots = match list_saves() {
+        Ok(slots) => slots,
+        Err(e) => {
+            msgbox(&format!("\n{}\n", e), 50, renderer);
+            return None;
+        }
+    };
+    if slots.is_empty() {
+        msgbox("\nNo saved game to load.\n", 24, renderer);
+        return None;
+    }
+    let mut page = 0;
+    loop {
+        let start = page * SAVES_PER_PAGE;
+        let end = cmp::min(start + SAVES_PER_PAGE, slots.len());
+        let mut options: Vec<String> = slots[start..end].iter().map(|slot| match slot.progress {
+            Ok((level, depth)) => format!("{}, level {} on depth {} ({})",
+                                          slot.name, level, depth, time_ago(slot.last_played)),
+            Err(_) => format!("{} (damaged save)", slot.name),
+        }).collect();
+        let paged = slots.len() > SAVES_PER_PAGE;
+        if paged {
+            options.push("More...".to_string());
+        }
+        match menu(header, &options, 60, renderer) {
+            Some(index) if start + index < end => return Some(slots.swap_remove(start + index)),
+            // past the last page goes back to the first
+            Some(_) if paged => page = if end == slots.len() { 0 } else { page + 1 },
+            _ => return None,
+        }
+    }
 }
-pub fn move_by(id: usize, dx: i32, dy: i32, map: &Map, objects: &mut [Object]) {
+
+pub fn move_by(id: EntityId, dx: i32, dy: i32, map: &Map, objects: &mut Entities) {
     let (x, y) = objects[id].pos();
     if !is_blocked(x + dx, y + dy, map, objects) {
         objects[id].set_pos(x + dx, y + dy);
@@ -397,104 +564,115 @@ pub fn move_by(id: usize, dx: i32, dy: i32, map: &Map, objects: &mut [Object]) {
 }
 
 
-pub fn is_blocked(x: i32, y: i32, map: &Map, objects: &[Object]) -> bool {
+pub fn is_blocked(x: i32, y: i32, map: &Map, objects: &Entities) -> bool {
     // first test the map tile
     if map[x as usize][y as usize].blocked {
         return true;
     }
     // now check for any blocking objects
-    objects.iter().any(|object| {
+    objects.iter().any(|(_, object)| {
         object.blocks && object.pos() == (x, y)
     })
 }
 
-pub fn render_all(tcod: &mut Tcod, objects: &[Object], game: &mut Game, fov_recompute: bool) {
-    if fov_recompute {
-        // recompute FOV if needed (the player moved or something)
-        let player = &objects[PLAYER];
-        tcod.fov.compute_fov(player.x, player.y, TORCH_RADIUS, FOV_LIGHT_WALLS, FOV_ALGO);
-
-        // go through all tiles, and set their background color
-        for y in 0..MAP_HEIGHT {
-            for x in 0..MAP_WIDTH {
-                let visible = tcod.fov.is_in_fov(x, y);
-                let wall = game.map[x as usize][y as usize].block_sight;
-                let color = match (visible, wall) {
-                    // outside of field of view:
-                    (false, true) => COLOR_DARK_WALL,
-                    (false, false) => COLOR_DARK_GROUND,
-                    // inside fov:
-                    (true, true) => COLOR_LIGHT_WALL,
-                    (true, false) => COLOR_LIGHT_GROUND,
-                };
+/// Draw the map, the objects in view and the panel, showing the names of
+/// whatever is at `look`
+pub fn render_all(renderer: &mut dyn Renderer, state: &GameState, look: (i32, i32)) {
+    let objects = &state.objects;
+    let game = &state.game;
+    // unexplored areas are black
+    renderer.clear();
 
-                let explored = &mut game.map[x as usize][y as usize].explored;
-                if visible {
-                    // since it's visible, explore it
-                    *explored = true;
-                }
-                if *explored {
-                    // show explored tiles only (any visible tile is explored already)
-                    // con.set_char_background(x, y, color, BackgroundFlag::Set);
-                    if wall {
-                        tcod.con.set_default_foreground(color);
-                        tcod.con.put_char(x, y, WALL, BackgroundFlag::Set);
-                    }
-                    else {
-                        tcod.con.set_default_foreground(color);
-                        tcod.con.put_char(x, y, FLOOR, BackgroundFlag::Set);
-                    }
-                }
+    // go through all tiles, and draw the ones that have been explored
+    for y in 0..MAP_HEIGHT {
+        for x in 0..MAP_WIDTH {
+            let visible = state.fov.is_in_fov(x, y);
+            let tile = &game.map[x as usize][y as usize];
+            // secret doors look just like the wall around them
+            let door = tile.door.filter(|&door| door != Door::Secret);
+            let color = match (visible, door) {
+                // doors, with the locked ones picked out while in view
+                (true, Some(Door::Locked)) => COLOR_LOCKED_DOOR,
+                (false, Some(_)) => COLOR_DARK_DOOR,
+                (true, Some(_)) => COLOR_LIGHT_DOOR,
+                // everything else has its own colors, darker outside of field of view
+                (false, None) => tile.kind.dark_color(),
+                (true, None) => tile.kind.lit_color(),
+            };
+            // traps that have been found stand out, in or out of view
+            let trap = tile.trap.filter(|trap| !trap.hidden);
+            let color = trap.map_or(color, |trap| trap.kind.color());
+
+            if tile.explored {
+                // show explored tiles only (any visible tile is explored already)
+                let glyph = match (door, trap) {
+                    (Some(Door::Open), _) => OPEN_DOOR,
+                    (Some(_), _) => CLOSED_DOOR,
+                    (None, Some(_)) => TRAP,
+                    (None, None) => tile.kind.glyph(),
+                };
+                renderer.put_glyph(x, y, glyph, color, None);
             }
         }
     }
 
     let mut to_draw: Vec<_> = objects.iter()
+        .map(|(_, o)| o)
         .filter(|o| {
-            tcod.fov.is_in_fov(o.x, o.y) ||
+            state.fov.is_in_fov(o.x, o.y) ||
                 (o.always_visible && game.map[o.x as usize][o.y as usize].explored)
         }).collect();
     // sort so that non-blocknig objects come first
     to_draw.sort_by(|o1, o2| { o1.blocks.cmp(&o2.blocks) });
     // draw the objects in the list
     for object in &to_draw {
-        object.draw(&mut tcod.con);
+        object.draw(renderer);
     }
 
-    // blit the contents of "con" to the root console
-    blit(&tcod.con, (0, 0), (MAP_WIDTH, MAP_HEIGHT), &mut tcod.root, (0, 0), 1.0, 1.0);
-
-    // prepare to render the GUI panel
-    tcod.panel.set_default_background(colors::BLACK);
-    tcod.panel.clear();
-
-    // print the game messages, one line at a time
+    // print the game messages in the panel, one line at a time
     let mut y = MSG_HEIGHT as i32;
-    for &(ref msg, color) in game.log.iter().rev() {
-        let msg_height = tcod.panel.get_height_rect(MSG_X, y, MSG_WIDTH, 0, msg);
-        y -= msg_height;
-        if y < 0 {
-            break;
+    'messages: for &(ref msg, color) in game.log.iter().rev() {
+        for line in wrap_text(msg, MSG_WIDTH).iter().rev() {
+            y -= 1;
+            if y < 0 {
+                break 'messages;
+            }
+            renderer.print(MSG_X, PANEL_Y + y, line, color);
         }
-        tcod.panel.set_default_foreground(color);
-        tcod.panel.print_rect(MSG_X, y, MSG_WIDTH, 0, msg);
     }
 
 
     // show the player's stats
     let hp = objects[PLAYER].fighter.map_or(0, |f| f.hp);
     let max_hp = objects[PLAYER].max_hp(game);
-    render_bar(&mut tcod.panel, 1, 1, BAR_WIDTH, "HP", hp, max_hp, colors::LIGHT_RED, colors::DARKER_RED);
+    render_bar(renderer, 1, PANEL_Y + 1, BAR_WIDTH, "HP", hp, max_hp, colors::LIGHT_RED, colors::DARKER_RED);
 
-    tcod.panel.print_ex(1, 3, BackgroundFlag::None, TextAlignment::Left,
-                        format!("Dungeon level: {}", game.dungeon_level));
+    renderer.print(1, PANEL_Y + 3, &format!("Dungeon level: {}", game.dungeon_level), colors::WHITE);
 
     // display names of objects under the mouse
-    tcod.panel.set_default_foreground(colors::LIGHT_GREY);
-    tcod.panel.print_ex(1, 0, BackgroundFlag::None, TextAlignment::Left,
-                        get_names_under_mouse(tcod.mouse, objects, &tcod.fov));
-
-    // blit the contents of `panel` to the root console
-    blit(&mut tcod.panel, (0, 0), (SCREEN_WIDTH, PANEL_HEIGHT), &mut tcod.root, (0, PANEL_Y), 1.0, 1.0);
+    let (look_x, look_y) = look;
+    renderer.print(1, PANEL_Y, &get_names_at(look_x, look_y, objects, &state.fov), colors::LIGHT_GREY);
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_cave_still_has_the_stairs_away_from_the_start() {
+        let templates = load_templates().unwrap();
+        // squares as big as the map, so the whole cave is the one area
+        let generator = CaveGenerator { area_size: MAP_WIDTH, ..CaveGenerator::default() };
+        for seed in 0..20 {
+            let (mut objects, mut game) = new_game(seed, &templates);
+            if build_level(&generator, &mut objects, 3, &mut game.rng, &templates).is_none() {
+                continue;
+            }
+            let stairs = objects.iter().find(|&(_, object)| object.name == "stairs").unwrap().1;
+            let (x, y) = objects[PLAYER].pos();
+            let distance = (stairs.x - x).pow(2) + (stairs.y - y).pow(2);
+            assert!(distance > 0, "the stairs are where the player starts, from seed {}", seed);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use rand::Rng;
+
+use crate::types::*;
+use crate::r#const::*;
+
+/// Roll for each prefab allowed on this level, and stamp the ones that come
+/// up into solid rock, tunnelling from their way in to the nearest floor.
+/// Returns where the stairs are if a prefab has them.
+pub fn place_prefabs(map: &mut Map, objects: &mut Entities, level: u32, rng: &mut GameRng,
+                     templates: &Templates) -> Option<(i32, i32)> {
+    let mut stairs = None;
+    for prefab in templates.prefabs.iter().filter(|prefab| prefab.allowed_on(level)) {
+        if rng.gen_range(0, 100) >= prefab.chance {
+            continue;
+        }
+        // keep a border of rock all round, so it doesn't open onto a room by accident
+        let (width, height) = (prefab.width(), prefab.height());
+        if width + 2 >= MAP_WIDTH || height + 2 >= MAP_HEIGHT {
+            continue;
+        }
+        for _ in 0..PREFAB_ATTEMPTS {
+            let x = rng.gen_range(1, MAP_WIDTH - width);
+            let y = rng.gen_range(1, MAP_HEIGHT - height);
+            let bounds = Rect::new(x - 1, y - 1, width + 1, height + 1);
+            if is_solid_rock(bounds, map) {
+                if let Some(prefab_stairs) = stamp_prefab(prefab, x, y, map, objects, level, templates) {
+                    stairs = stairs.or(Some(prefab_stairs));
+                }
+                break;
+            }
+        }
+    }
+    stairs
+}
+
+/// whether every tile in a rectangle (edges included) is wall
+pub fn is_solid_rock(area: Rect, map: &Map) -> bool {
+    (area.x1..=area.x2).all(|x| (area.y1..=area.y2).all(|y| map[x as usize][y as usize].blocked))
+}
+
+/// Draw a prefab onto the map with its top left corner at (x, y), spawning
+/// its monsters and items and joining it up. Returns where its stairs are, if
+/// it has some.
+fn stamp_prefab(prefab: &Prefab, x: i32, y: i32, map: &mut Map, objects: &mut Entities,
+                level: u32, templates: &Templates) -> Option<(i32, i32)> {
+    let mut stairs = None;
+    let mut entrances = vec![];
+    for (dx, dy, c) in prefab.cells() {
+        let (tile_x, tile_y) = (x + dx, y + dy);
+        match c {
+            ' ' => continue,
+            '#' => {
+                map[tile_x as usize][tile_y as usize] = Tile::wall();
+                continue;
+            }
+            '<' => stairs = Some((tile_x, tile_y)),
+            '+' => {
+                map[tile_x as usize][tile_y as usize] = Tile::door(Door::Closed);
+                entrances.push((dx, dy));
+                continue;
+            }
+            _ => {}
+        }
+        map[tile_x as usize][tile_y as usize] = Tile::empty();
+
+        let monster = prefab.monsters.get(&c)
+            .and_then(|name| templates.monsters.iter().find(|monster| &monster.name == name));
+        if let Some(monster) = monster {
+            objects.spawn(monster.spawn(tile_x, tile_y, level));
+        }
+        let item = prefab.items.get(&c)
+            .and_then(|name| templates.items.iter().find(|item| &item.name == name));
+        if let Some(item) = item {
+            objects.spawn(item.spawn(tile_x, tile_y));
+        }
+    }
+
+    // tunnel out from just outside each way in to the nearest floor, without
+    // going back through the prefab
+    let bounds = Rect::new(x, y, prefab.width() - 1, prefab.height() - 1);
+    let inside = |(x, y): (i32, i32)| x >= bounds.x1 && x <= bounds.x2 && y >= bounds.y1 && y <= bounds.y2;
+    for (dx, dy) in entrances {
+        let outward = if dy == 0 {
+            (0, -1)
+        } else if dy == prefab.height() - 1 {
+            (0, 1)
+        } else if dx == 0 {
+            (-1, 0)
+        } else {
+            (1, 0)
+        };
+        let outside = (x + dx + outward.0, y + dy + outward.1);
+        if !inside(outside) {
+            let floor = map.clone();
+            dig_tunnel(map, outside, |(x, y)| !floor[x as usize][y as usize].blocked, |tile| !inside(tile));
+        }
+    }
+    stairs
+}
@@ -0,0 +1,44 @@
+use crate::types::*;
+use crate::r#const::*;
+
+/// Every item and staircase that the player can't walk to from where they
+/// are, counting doors as open. An empty list means the level can be
+/// finished and all its loot picked up.
+pub fn unreachable_objects(map: &Map, objects: &Entities) -> Vec<EntityId> {
+    let reachable = reachable_tiles(map, objects[PLAYER].pos());
+    objects.iter()
+        .filter(|&(_, object)| object.item.is_some() || object.name == "stairs")
+        .filter(|&(_, object)| !reachable[object.x as usize][object.y as usize])
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// Dig a tunnel from everything `unreachable_objects` finds to the nearest
+/// tile the player can get to. Returns false if something couldn't be joined
+/// up, in which case the level should be thrown away.
+pub fn repair_reachability(map: &mut Map, objects: &Entities) -> bool {
+    loop {
+        let unreachable = unreachable_objects(map, objects);
+        let id = match unreachable.first() {
+            Some(&id) => id,
+            None => return true,
+        };
+        let reachable = reachable_tiles(map, objects[PLAYER].pos());
+        let dug = dig_tunnel(map, objects[id].pos(),
+                             |(x, y)| reachable[x as usize][y as usize], |_| true);
+        if !dug {
+            return false;
+        }
+    }
+}
+
+/// which tiles can be walked to from `start`
+fn reachable_tiles(map: &Map, start: (i32, i32)) -> Vec<Vec<bool>> {
+    let mut reachable = vec![vec![false; MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+    if map[start.0 as usize][start.1 as usize].passable() {
+        for (x, y) in connected_region(map, start, Tile::passable) {
+            reachable[x as usize][y as usize] = true;
+        }
+    }
+    reachable
+}
@@ -0,0 +1,105 @@
+use std::io::{Write, ErrorKind};
+use std::fs::{self, OpenOptions};
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+use crate::types::*;
+use crate::r#const::*;
+use super::{render_all, msgbox, load_data_file, save_dir, file_name};
+
+/// Where replays are written: `REPLAY_DIR` in the save directory
+pub fn replay_dir() -> PathBuf {
+    save_dir().join(REPLAY_DIR)
+}
+
+/// Write the game so far out as a new replay in `replay_dir()`, named
+/// `<seed>-<name>-<time>.json` so it never replaces an earlier one.
+/// Returns the path it was written to, or None if the game has no seed to
+/// replay it from.
+pub fn save_replay(state: &GameState) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let seed = match state.game.seed {
+        Some(seed) => seed,
+        None => return Ok(None),
+    };
+    let replay = Replay {
+        seed,
+        commands: state.game.history.clone(),
+        outcome: Outcome::of(state),
+    };
+    let replay_data = serde_json::to_string(&replay)?;
+    fs::create_dir_all(replay_dir())?;
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+    let stem = format!("{}-{}-{}", seed, file_name(&state.game.name), time);
+    // two games can end in the same second, so count up until a name is free
+    let mut path = replay_dir().join(format!("{}.json", stem));
+    let mut copies = 1;
+    loop {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(replay_data.as_bytes())?;
+                return Ok(Some(path));
+            }
+            Err(ref e) if e.kind() == ErrorKind::AlreadyExists => {
+                copies += 1;
+                path = replay_dir().join(format!("{}-{}.json", stem, copies));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+pub fn load_replay(path: &str) -> Result<Replay, Box<dyn Error>> {
+    load_data_file(path)
+}
+
+/// Check a replayed game ended up where the recording did
+pub fn check_replay(replay: &Replay, state: &GameState) -> Result<(), String> {
+    let outcome = Outcome::of(state);
+    if outcome == replay.outcome {
+        Ok(())
+    } else {
+        Err(format!("Replay diverged from the recorded game.\nrecorded: {:?}\nreplayed: {:?}",
+                    replay.outcome, outcome))
+    }
+}
+
+/// Play a replay through without a window, returning the final state if it matches
+pub fn run_replay(replay: &Replay, templates: &Templates) -> Result<GameState, String> {
+    let mut state = GameState::new(replay.seed, templates.clone());
+    for &command in &replay.commands {
+        state.step(command);
+    }
+    check_replay(replay, &state)?;
+    Ok(state)
+}
+
+/// Play a replay back on screen, one command per frame. Escape skips to the end.
+pub fn watch_replay(renderer: &mut dyn Renderer, replay: &Replay, templates: &Templates) {
+    let mut state = GameState::new(replay.seed, templates.clone());
+    let mut watching = true;
+
+    for &command in &replay.commands {
+        if renderer.is_closed() {
+            return;
+        }
+        if let Some(Input::Key(key)) = renderer.poll_input() {
+            if key.code == KeyCode::Escape {
+                watching = false;
+            }
+        }
+        if watching {
+            render_all(renderer, &state, (-1, -1));
+            renderer.flush();
+        }
+        state.step(command);
+    }
+
+    render_all(renderer, &state, (-1, -1));
+    let result = match check_replay(replay, &state) {
+        Ok(()) => "\nThe replay matches the recorded game.\n",
+        Err(_) => "\nThe replay has diverged from the recorded game!\n",
+    };
+    msgbox(result, 50, renderer);
+}
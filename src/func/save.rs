@@ -0,0 +1,512 @@
+use std::io::{BufRead, BufReader, Read, Write, ErrorKind};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde_json::Value;
+
+use crate::types::*;
+use crate::r#const::*;
+use super::xdg_dir;
+
+/// What goes in the save file. `version` comes first so older and newer
+/// saves can be told apart before the rest is read.
+#[derive(Serialize)]
+struct SaveFile<'a> {
+    version: u32,
+    summary: SaveSummary,
+    objects: &'a Entities,
+    game: &'a Game,
+}
+
+impl<'a> SaveFile<'a> {
+    fn new(objects: &'a Entities, game: &'a Game) -> Self {
+        SaveFile {
+            version: SAVE_VERSION,
+            summary: summarise(objects, game),
+            objects,
+            game,
+        }
+    }
+}
+
+/// The version and summary of a save file, for listing it. The objects and
+/// game are still parsed past, but never built.
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+    summary: Option<SaveSummary>,
+}
+
+#[derive(Deserialize)]
+struct LoadedSave {
+    objects: Entities,
+    game: Game,
+}
+
+/// Upgrades a save from the format version it's listed under to the next one
+type Migration = fn(Value) -> Result<Value, String>;
+
+/// One migration per old format version, in order. Changing what gets saved
+/// means bumping `SAVE_VERSION` and adding a migration from the old format here.
+const MIGRATIONS: &[Migration] = &[
+    migrate_v0,
+    migrate_v1,
+    migrate_v2,
+    migrate_v3,
+    migrate_v4,
+    migrate_v5,
+    migrate_v6,
+];
+
+/// Where saves are kept: `$XDG_DATA_HOME/rouge`, or `~/.local/share/rouge`
+/// if that isn't set
+pub fn save_dir() -> PathBuf {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// The save slot for a character, named after them
+pub fn slot_path(name: &str) -> PathBuf {
+    save_dir().join(format!("{}.{}", file_name(name), SAVE_EXTENSION))
+}
+
+/// A character's name made safe to use in a file name. Anything but letters,
+/// digits, `-` and `_` is percent-encoded, so no two names end up the same.
+pub fn file_name(name: &str) -> String {
+    let mut file_name = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || c == '-' || c == '_' {
+            file_name.push(c);
+        } else {
+            for byte in c.to_string().bytes() {
+                file_name.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+    file_name
+}
+
+/// Save into the slot for the character the game belongs to. The save is
+/// written to a temporary file first and then moved over the old one, so a
+/// crash part way through leaves the previous save intact.
+pub fn save_game(objects: &Entities, game: &Game, format: SaveFormat) -> Result<(), SaveError> {
+    let save_data = serde_json::to_vec(&SaveFile::new(objects, game))?;
+    fs::create_dir_all(save_dir())?;
+    let path = slot_path(&game.name);
+    let temp_path = path.with_extension(format!("{}.tmp", SAVE_EXTENSION));
+    {
+        let mut file = File::create(&temp_path)?;
+        match format {
+            SaveFormat::Json => file.write_all(&save_data)?,
+            SaveFormat::Compressed => {
+                let mut encoder = GzEncoder::new(&mut file, Compression::default());
+                encoder.write_all(&save_data)?;
+                encoder.finish()?;
+            }
+        }
+        file.sync_all()?;
+    }
+    fs::rename(&temp_path, &path)?;
+    // the rename is only safe on disk once the directory holding it is too
+    #[cfg(unix)]
+    File::open(save_dir())?.sync_all()?;
+    Ok(())
+}
+
+pub fn load_game(path: &Path, templates: &Templates) -> Result<GameState, SaveError> {
+    let (objects, game) = read_save(path)?;
+    Ok(GameState::from_parts(objects, game, templates.clone()))
+}
+
+/// Read a save file in either format, upgrading it from older versions if needed
+pub fn read_save(path: &Path) -> Result<(Entities, Game), SaveError> {
+    let mut save_data = vec![];
+    let mut file = File::open(path)?;
+    file.read_to_end(&mut save_data)?;
+    let save = if save_data.starts_with(GZIP_MAGIC) {
+        serde_json::from_reader(GzDecoder::new(&save_data[..]))?
+    } else {
+        serde_json::from_slice(&save_data)?
+    };
+    let save = upgrade_save(save)?;
+    let LoadedSave { objects, game } = serde_json::from_value(save)?;
+    Ok((objects, game))
+}
+
+/// The name, level and depth of the character in a save, without building its
+/// objects and game (though the whole file is read, and decompressed). Saves
+/// from before there was a summary are loaded in full.
+pub fn read_summary(path: &Path) -> Result<SaveSummary, SaveError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let header: Result<SaveHeader, _> = if reader.fill_buf()?.starts_with(GZIP_MAGIC) {
+        serde_json::from_reader(GzDecoder::new(reader))
+    } else {
+        serde_json::from_reader(reader)
+    };
+    match header {
+        Ok(SaveHeader { version, summary: Some(summary) }) if version <= SAVE_VERSION => Ok(summary),
+        _ => read_save(path).map(|(objects, game)| summarise(&objects, &game)),
+    }
+}
+
+fn summarise(objects: &Entities, game: &Game) -> SaveSummary {
+    SaveSummary {
+        name: game.name.clone(),
+        level: objects[PLAYER].level,
+        dungeon_level: game.dungeon_level,
+    }
+}
+
+/// Every save in the save directory, most recently played first. Entries
+/// that can't be looked at are left out rather than spoiling the whole list.
+pub fn list_saves() -> Result<Vec<SaveSlot>, SaveError> {
+    let entries = match fs::read_dir(save_dir()) {
+        Ok(entries) => entries,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let mut slots = vec![];
+    for entry in entries {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(_) => continue,
+        };
+        if path.extension().map_or(true, |extension| extension != SAVE_EXTENSION) {
+            continue;
+        }
+        let last_played = match path.metadata().and_then(|metadata| metadata.modified()) {
+            Ok(last_played) => last_played,
+            Err(_) => continue,
+        };
+        let summary = read_summary(&path);
+        let name = match summary {
+            Ok(ref summary) => summary.name.clone(),
+            Err(_) => path.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+        };
+        slots.push(SaveSlot {
+            name,
+            path,
+            last_played,
+            progress: summary.map(|summary| (summary.level, summary.dungeon_level)),
+        });
+    }
+    slots.sort_by(|a, b| b.last_played.cmp(&a.last_played));
+    Ok(slots)
+}
+
+/// Move saves from older versions of the game to where `save_game` writes
+/// them now: slots that were named differently, and the one save that was
+/// left in the working directory. That goes into its own slot, unless that
+/// character already has one. Either way the old file is renamed, so this
+/// only happens once.
+pub fn import_legacy_save(format: SaveFormat) -> Result<Option<LegacyImport>, SaveError> {
+    rename_old_slots()?;
+    let legacy_path = Path::new(LEGACY_SAVE_FILE);
+    if !legacy_path.exists() {
+        return Ok(None);
+    }
+    let (objects, game) = read_save(legacy_path)?;
+    let imported = if slot_path(&game.name).exists() {
+        LegacyImport::SlotTaken(game.name)
+    } else {
+        save_game(&objects, &game, format)?;
+        LegacyImport::Moved(game.name)
+    };
+    // keep the original around rather than deleting it
+    fs::rename(legacy_path, format!("{}.imported", LEGACY_SAVE_FILE))?;
+    Ok(Some(imported))
+}
+
+/// Move each slot named the way older versions did it to its name now, unless
+/// something's there already
+fn rename_old_slots() -> Result<(), SaveError> {
+    let entries = match fs::read_dir(save_dir()) {
+        Ok(entries) => entries,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        if path.extension().map_or(true, |extension| extension != SAVE_EXTENSION) {
+            continue;
+        }
+        // a save that can't be read stays where it is, and shows up as damaged
+        if let Ok(summary) = read_summary(&path) {
+            let slot = slot_path(&summary.name);
+            if slot != path && !slot.exists() {
+                fs::rename(&path, &slot)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Bring a save of any known format version up to the current one
+pub fn upgrade_save(mut save: Value) -> Result<Value, SaveError> {
+    // saves from before there was a header are just an `[objects, game]` pair
+    let mut version = match save.get("version") {
+        Some(version) => match version.as_u64() {
+            Some(version) => version as u32,
+            None => return Err(SaveError::Migration {
+                from: 0,
+                reason: format!("the format version {} isn't a number", version),
+            }),
+        },
+        None => 0,
+    };
+    if version > SAVE_VERSION {
+        return Err(SaveError::TooNew { version });
+    }
+    while version < SAVE_VERSION {
+        save = MIGRATIONS[version as usize](save)
+            .map_err(|reason| SaveError::Migration { from: version, reason })?;
+        version += 1;
+        save["version"] = Value::from(version);
+    }
+    Ok(save)
+}
+
+/// v0 -> v1: add the header, move objects into an entity store, give the
+/// game an RNG (but no seed), and turn the old per-item kinds into `Equip`
+fn migrate_v0(save: Value) -> Result<Value, String> {
+    let (mut objects, mut game) = match save {
+        Value::Array(mut pair) => {
+            if pair.len() != 2 {
+                return Err("expected an [objects, game] pair".into());
+            }
+            let game = pair.pop().unwrap();
+            (pair.pop().unwrap(), game)
+        }
+        _ => return Err("expected an [objects, game] pair".into()),
+    };
+
+    let objects = match objects.as_array_mut() {
+        Some(objects) => objects.drain(..).collect::<Vec<_>>(),
+        None => return Err("the objects aren't a list".into()),
+    };
+    let mut entries = vec![];
+    for mut object in objects {
+        migrate_v0_item(&mut object)?;
+        entries.push(json!({ "generation": 0, "object": object }));
+    }
+
+    if !game.is_object() {
+        return Err("the game isn't an object".into());
+    }
+    if let Some(inventory) = game.get_mut("inventory").and_then(Value::as_array_mut) {
+        for item in inventory {
+            migrate_v0_item(item)?;
+        }
+    }
+    // these games weren't seeded, so carry on from a fresh RNG. Without the
+    // seed or the moves so far they can't be replayed, so there's no seed.
+    game["seed"] = Value::Null;
+    game["rng"] = serde_json::to_value(GameRng::new(rand::random())).map_err(|e| e.to_string())?;
+    game["history"] = json!([]);
+
+    Ok(json!({
+        "objects": { "entries": entries, "free": [] },
+        "game": game,
+    }))
+}
+
+/// v1 -> v2: characters have names, which older saves didn't ask for
+fn migrate_v1(mut save: Value) -> Result<Value, String> {
+    match save.get_mut("game") {
+        Some(game) if game.is_object() => game["name"] = json!(DEFAULT_NAME),
+        _ => return Err("the save has no game".into()),
+    }
+    Ok(save)
+}
+
+/// v2 -> v3: levels are kept when the player leaves them, and older saves
+/// haven't kept any
+fn migrate_v2(mut save: Value) -> Result<Value, String> {
+    match save.get_mut("game") {
+        Some(game) if game.is_object() => game["levels"] = json!({}),
+        _ => return Err("the save has no game".into()),
+    }
+    Ok(save)
+}
+
+/// v3 -> v4: tiles can be doors, and none in older saves are
+fn migrate_v3(save: Value) -> Result<Value, String> {
+    migrate_tiles(save, |tile| tile["door"] = Value::Null)
+}
+
+/// v4 -> v5: tiles have a kind, and older saves only had walls and floor
+fn migrate_v4(save: Value) -> Result<Value, String> {
+    migrate_tiles(save, |tile| {
+        let wall = tile["blocked"] == json!(true) && tile["door"].is_null();
+        tile["kind"] = json!(if wall { "Wall" } else { "Floor" });
+    })
+}
+
+/// v5 -> v6: tiles can have traps, and there weren't any in older saves
+fn migrate_v5(save: Value) -> Result<Value, String> {
+    migrate_tiles(save, |tile| tile["trap"] = Value::Null)
+}
+
+/// v6 -> v7: saves start with a summary for the save menu, made here from
+/// the player and game
+fn migrate_v6(mut save: Value) -> Result<Value, String> {
+    let level = save["objects"]["entries"][0]["object"]["level"].clone();
+    let (name, dungeon_level) = (save["game"]["name"].clone(), save["game"]["dungeon_level"].clone());
+    if !level.is_i64() || !name.is_string() || !dungeon_level.is_u64() {
+        return Err("couldn't find the player's name, level and depth".into());
+    }
+    save["summary"] = json!({ "name": name, "level": level, "dungeon_level": dungeon_level });
+    Ok(save)
+}
+
+/// change every tile of every map in the save, current level and kept ones alike
+fn migrate_tiles<F: Fn(&mut Value)>(mut save: Value, migrate_tile: F) -> Result<Value, String> {
+    let game = match save.get_mut("game").and_then(Value::as_object_mut) {
+        Some(game) => game,
+        None => return Err("the save has no game".into()),
+    };
+    let mut maps = vec![];
+    for (key, value) in game.iter_mut() {
+        match &key[..] {
+            "map" => maps.push(value),
+            "levels" => if let Some(levels) = value.as_object_mut() {
+                maps.extend(levels.values_mut().map(|level| &mut level["map"]));
+            },
+            _ => {}
+        }
+    }
+    for map in maps {
+        let columns = map.as_array_mut().ok_or("the map isn't a list of columns")?;
+        for column in columns {
+            let tiles = column.as_array_mut().ok_or("a map column isn't a list of tiles")?;
+            for tile in tiles {
+                if !tile.is_object() {
+                    return Err("a map tile isn't an object".into());
+                }
+                migrate_tile(tile);
+            }
+        }
+    }
+    Ok(save)
+}
+
+fn migrate_v0_item(object: &mut Value) -> Result<(), String> {
+    let kind = match object.get("item").and_then(Value::as_str) {
+        Some(kind) => kind.to_string(),
+        None => return Ok(()),
+    };
+    match &kind[..] {
+        "Heal" | "Lightning" | "Confuse" | "Fireball" => {}
+        "BronzeSword" | "IronSword" | "GreatAxe" | "WarHammer" | "WoodShield" | "IronShield" |
+        "Dagger" | "ClothShirt" | "ClothPants" | "LeatherWristGaurds" | "LeatherHat" |
+        "LeatherChest" | "LeatherKneeGaurds" => object["item"] = json!("Equip"),
+        _ => return Err(format!("unknown item kind {}", kind)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::func::{new_game, load_templates};
+
+    /// A save the way the game wrote them before there was a format version:
+    /// a bare `[objects, game]` pair, with none of what's been added since
+    fn v0_save() -> Value {
+        let templates = load_templates().unwrap();
+        let (objects, game) = new_game(1, &templates);
+        let save = serde_json::to_value(SaveFile::new(&objects, &game)).unwrap();
+
+        // equipment had a kind of its own per item, and there were no keys
+        let old_item = |object: &mut Value| if object["item"] == "Equip" {
+            object["item"] = json!("IronSword");
+        };
+        let mut objects: Vec<Value> = save["objects"]["entries"].as_array().unwrap().iter()
+            .map(|entry| entry["object"].clone())
+            .filter(|object| !object.is_null() && object["item"] != "Key")
+            .collect();
+        objects.iter_mut().for_each(old_item);
+
+        let mut game = save["game"].clone();
+        for field in &["seed", "rng", "history", "name", "levels"] {
+            game.as_object_mut().unwrap().remove(*field);
+        }
+        game["inventory"].as_array_mut().unwrap().iter_mut().for_each(old_item);
+        for column in game["map"].as_array_mut().unwrap() {
+            for tile in column.as_array_mut().unwrap() {
+                let tile = tile.as_object_mut().unwrap();
+                for field in &["door", "kind", "trap"] {
+                    tile.remove(*field);
+                }
+            }
+        }
+        json!([objects, game])
+    }
+
+    #[test]
+    fn one_migration_per_old_version() {
+        assert_eq!(MIGRATIONS.len(), SAVE_VERSION as usize);
+    }
+
+    #[test]
+    fn v0_saves_upgrade_to_the_current_version() {
+        let save = upgrade_save(v0_save()).unwrap();
+        assert_eq!(save["version"], json!(SAVE_VERSION));
+
+        let SaveHeader { summary, .. } = serde_json::from_value(save.clone()).unwrap();
+        assert_eq!(summary, Some(SaveSummary { name: DEFAULT_NAME.to_string(), level: 1, dungeon_level: 1 }));
+        let LoadedSave { objects, game } = serde_json::from_value(save).unwrap();
+        assert_eq!(game.seed, None, "a migrated game has no seed to replay it from");
+        assert!(game.history.is_empty());
+        assert_eq!(game.name, DEFAULT_NAME);
+        assert!(game.levels.is_empty());
+        assert!(game.inventory.iter().all(|item| item.item == Some(Item::Equip)));
+        assert!(objects.with_item().all(|(_, item)| item.item != Some(Item::Key)));
+        for tile in game.map.iter().flat_map(|column| column.iter()) {
+            assert!(tile.door.is_none() && tile.trap.is_none());
+            let kind = if tile.blocked { TileKind::Wall } else { TileKind::Floor };
+            assert_eq!(tile.kind, kind);
+        }
+    }
+
+    #[test]
+    fn current_saves_are_left_alone() {
+        let templates = load_templates().unwrap();
+        let (objects, game) = new_game(2, &templates);
+        let save = serde_json::to_value(SaveFile::new(&objects, &game)).unwrap();
+        assert_eq!(upgrade_save(save.clone()).unwrap(), save);
+    }
+
+    #[test]
+    fn every_name_has_its_own_slot() {
+        let names = ["Bob Smith", "Bob_Smith", "Bob.Smith", "Bob%20Smith", "Bob-Smith", "Bob/Smith", "Bøb"];
+        let mut slots: Vec<_> = names.iter().map(|name| slot_path(name)).collect();
+        slots.sort();
+        slots.dedup();
+        assert_eq!(slots.len(), names.len());
+        assert_eq!(slot_path("Bob_Smith").file_name().unwrap(), "Bob_Smith.sav");
+        assert_eq!(slot_path("Bob Smith").file_name().unwrap(), "Bob%20Smith.sav");
+        assert_eq!(slot_path("../Bob").file_name().unwrap(), "%2E%2E%2FBob.sav");
+    }
+
+    #[test]
+    fn newer_saves_are_refused() {
+        match upgrade_save(json!({ "version": SAVE_VERSION + 1 })) {
+            Err(SaveError::TooNew { version }) => assert_eq!(version, SAVE_VERSION + 1),
+            other => panic!("expected TooNew, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn broken_saves_say_where_they_failed() {
+        match upgrade_save(json!([1, 2, 3])) {
+            Err(SaveError::Migration { from: 0, .. }) => {}
+            other => panic!("expected a failed migration from v0, got {:?}", other),
+        }
+        match upgrade_save(json!({ "version": "two" })) {
+            Err(SaveError::Migration { .. }) => {}
+            other => panic!("expected a bad version, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use rand::Rng;
+use crate::types::colors;
+
+use crate::types::*;
+use crate::r#const::*;
+use super::*;
+
+/// Maybe dig a small room full of treasure into the rock, with a tunnel out
+/// to the rest of the level from behind a secret door
+pub fn place_secret_room(map: &mut Map, objects: &mut Entities, level: u32, rng: &mut GameRng,
+                         templates: &Templates) {
+    if rng.gen_range(0, 100) >= SECRET_ROOM_CHANCE {
+        return;
+    }
+    for _ in 0..SECRET_ROOM_ATTEMPTS {
+        // walls included, like the rooms `create_room` makes
+        let (w, h) = (rng.gen_range(4, 7), rng.gen_range(4, 6));
+        let x = rng.gen_range(1, MAP_WIDTH - w - 1);
+        let y = rng.gen_range(1, MAP_HEIGHT - h - 1);
+        let room = Rect::new(x, y, w, h);
+        // keep a border of rock all round, so it doesn't open onto anything by accident
+        if !is_solid_rock(Rect::new(x - 1, y - 1, w + 2, h + 2), map) {
+            continue;
+        }
+
+        // the secret door goes somewhere along one of the walls, and the
+        // tunnel starts just outside it
+        let ((door_x, door_y), (out_x, out_y)) = match rng.gen_range(0, 4) {
+            0 => ((rng.gen_range(x + 1, x + w), y), (0, -1)),
+            1 => ((rng.gen_range(x + 1, x + w), y + h), (0, 1)),
+            2 => ((x, rng.gen_range(y + 1, y + h)), (-1, 0)),
+            _ => ((x + w, rng.gen_range(y + 1, y + h)), (1, 0)),
+        };
+        let before = map.clone();
+        create_room(room, map);
+        map[door_x as usize][door_y as usize] = Tile::door(Door::Secret);
+        let inside = |(x, y): (i32, i32)| x >= room.x1 && x <= room.x2 && y >= room.y1 && y <= room.y2;
+        let dug = dig_tunnel(map, (door_x + out_x, door_y + out_y),
+                             |(x, y)| !before[x as usize][y as usize].blocked, |tile| !inside(tile));
+        if !dug {
+            *map = before;
+            continue;
+        }
+
+        let area = Area::from(room);
+        for _ in 0..rng.gen_range(1, MAX_SECRET_ROOM_ITEMS + 1) {
+            let (x, y) = area.random_tile(rng);
+            if let Some(item) = random_item(level, rng, templates) {
+                objects.spawn(item.spawn(x, y));
+            }
+        }
+        return;
+    }
+}
+
+/// Secret doors only ever hide something extra: turn the ones that stand
+/// between the player and the stairs into plain doors, nearest first.
+pub fn reveal_way_to_stairs(map: &mut Map, objects: &Entities) {
+    let stairs = match objects.iter().find(|&(_, object)| object.name == "stairs") {
+        Some((_, stairs)) => stairs.pos(),
+        None => return,
+    };
+    loop {
+        let region = connected_region(map, objects[PLAYER].pos(), |tile| {
+            tile.passable() && tile.door != Some(Door::Secret)
+        });
+        if region.contains(&stairs) {
+            return;
+        }
+        let secret_door = region.iter()
+            .flat_map(|&(x, y)| (-1..=1).flat_map(move |dx| (-1..=1).map(move |dy| (x + dx, y + dy))))
+            .find(|&(x, y)| map[x as usize][y as usize].door == Some(Door::Secret));
+        match secret_door {
+            Some((x, y)) => map[x as usize][y as usize] = Tile::door(Door::Closed),
+            // there's some other reason, which isn't for this to fix
+            None => return,
+        }
+    }
+}
+
+/// The chance (in percent) of a turn spent searching finding each hidden
+/// thing next to the player, better the more agile they are
+pub fn search_chance(objects: &Entities) -> u32 {
+    let agility = objects[PLAYER].fighter.map_or(0, |f| f.base_defense.max(0) as u32);
+    cmp::min(100, SEARCH_CHANCE + agility * SEARCH_CHANCE_PER_AGILITY)
+}
+
+/// Roll `chance` (in percent) for each hidden trap and secret door next to
+/// the player, and reveal the ones that come up. Returns how many were found.
+pub fn search_around(chance: u32, objects: &Entities, game: &mut Game, fov: &mut FovMap) -> usize {
+    let (player_x, player_y) = objects[PLAYER].pos();
+    let mut found = 0;
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            let (x, y) = (player_x + dx, player_y + dy);
+            if x < 0 || y < 0 || x >= MAP_WIDTH || y >= MAP_HEIGHT {
+                continue;
+            }
+            let tile = game.map[x as usize][y as usize];
+            if let Some(trap) = tile.trap {
+                if trap.hidden && game.rng.gen_range(0, 100) < chance {
+                    game.map[x as usize][y as usize].trap = Some(Trap { kind: trap.kind, hidden: false });
+                    game.log.add(format!("You find a {}.", trap.kind.name()), colors::LIGHT_CYAN);
+                    found += 1;
+                }
+            }
+            if tile.door == Some(Door::Secret) && game.rng.gen_range(0, 100) < chance {
+                set_door(x, y, Door::Closed, &mut game.map, fov);
+                game.log.add("You find a secret door!", colors::LIGHT_CYAN);
+                found += 1;
+            }
+        }
+    }
+    found
+}
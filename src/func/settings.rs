@@ -0,0 +1,69 @@
+use std::env;
+use std::io::{Read, Write, ErrorKind};
+use std::fs::{self, File};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+
+use crate::types::*;
+use crate::r#const::*;
+
+/// `$<variable>/rouge`, or `~/<fallback>/rouge` if the variable isn't set,
+/// following the XDG base directory conventions
+pub fn xdg_dir(variable: &str, fallback: &str) -> PathBuf {
+    let base = env::var_os(variable)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(fallback)))
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join("rouge")
+}
+
+/// Where settings are kept: `$XDG_CONFIG_HOME/rouge`, or `~/.config/rouge`
+pub fn config_dir() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// Read the settings and keymap files, falling back to the defaults for
+/// anything they leave out. It's an error for the keymap to give one key to
+/// more than one action, since there'd be no telling which it does.
+pub fn load_settings() -> Result<Settings, Box<dyn Error>> {
+    let mut settings: Settings = read_config_file(SETTINGS_FILE)?.unwrap_or_default();
+    let custom_keys: Option<Keymap> = read_config_file(KEYMAP_FILE)?;
+    if let Some(ref custom_keys) = custom_keys {
+        let duplicates: Vec<_> = custom_keys.duplicates().into_iter()
+            .map(|(key, actions)| {
+                let actions: Vec<_> = actions.iter().map(Action::to_string).collect();
+                format!("\"{}\" is bound to {}", key, actions.join(" and "))
+            })
+            .collect();
+        if !duplicates.is_empty() {
+            return Err(format!("{} gives keys more than one action: {}", KEYMAP_FILE,
+                               duplicates.join(", ")).into());
+        }
+    }
+    settings.custom_keys = custom_keys;
+    Ok(settings)
+}
+
+/// Read a JSON file from the config directory, or None if it isn't there
+fn read_config_file<T: DeserializeOwned>(name: &str) -> Result<Option<T>, Box<dyn Error>> {
+    let path = config_dir().join(name);
+    let mut json = String::new();
+    match File::open(&path) {
+        Ok(mut file) => file.read_to_string(&mut json)?,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let data = serde_json::from_str(&json)
+        .map_err(|e| format!("Couldn't parse {}: {}", path.display(), e))?;
+    Ok(Some(data))
+}
+
+pub fn save_settings(settings: &Settings) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(config_dir())?;
+    let mut file = File::create(config_dir().join(SETTINGS_FILE))?;
+    file.write_all(serde_json::to_string_pretty(settings)?.as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use rand::Rng;
+use rand::distributions::{Weighted, WeightedChoice, IndependentSample};
+use crate::types::colors;
+
+use crate::types::*;
+use crate::r#const::*;
+use super::*;
+
+/// Maybe put a patch of water, lava, chasm or rubble in an area, depending on the level
+pub fn place_terrain(area: &Area, map: &mut Map, level: u32, rng: &mut GameRng) {
+    // terrain random table: None is a plain area
+    let terrain_chances = &mut [
+        Weighted {weight: 60, item: None},
+        Weighted {weight: from_dungeon_level(&[Transition {level: 1, value: 15}], level),
+                  item: Some(TileKind::ShallowWater)},
+        Weighted {weight: from_dungeon_level(&[Transition {level: 1, value: 15}], level),
+                  item: Some(TileKind::Rubble)},
+        Weighted {weight: from_dungeon_level(&[Transition {level: 3, value: 10}], level),
+                  item: Some(TileKind::DeepWater)},
+        Weighted {weight: from_dungeon_level(&[Transition {level: 3, value: 5}], level),
+                  item: Some(TileKind::Chasm)},
+        Weighted {weight: from_dungeon_level(&[Transition {level: 4, value: 5},
+                                               Transition {level: 7, value: 10}], level),
+                  item: Some(TileKind::Lava)},
+    ];
+    let terrain_choice = WeightedChoice::new(terrain_chances);
+    let kind = match terrain_choice.ind_sample(rng) {
+        Some(kind) => kind,
+        None => return,
+    };
+
+    // wander about the area from a random spot, changing the floor on the way
+    let size = rng.gen_range(3, MAX_TERRAIN_SIZE + 1);
+    let (mut x, mut y) = area.random_tile(rng);
+    for _ in 0..size * 2 {
+        let in_area = area.tiles.contains(&(x, y));
+        let tile = &mut map[x as usize][y as usize];
+        if in_area && tile.kind == TileKind::Floor && tile.door.is_none() {
+            *tile = Tile::new(kind);
+        }
+        let (dx, dy) = *rng.choose(&[(1, 0), (-1, 0), (0, 1), (0, -1)]).unwrap();
+        if area.tiles.contains(&(x + dx, y + dy)) {
+            x += dx;
+            y += dy;
+        }
+    }
+}
+
+/// Whatever the player just walked onto does its thing to them. Returns
+/// whether they left the level.
+pub fn player_enters_tile(objects: &mut Entities, game: &mut Game, fov: &mut FovMap,
+                          templates: &Templates) -> bool {
+    let (x, y) = objects[PLAYER].pos();
+    match game.map[x as usize][y as usize].kind.effect() {
+        Some(TileEffect::SweepAway) => {
+            let mut inventory_id = 0;
+            while inventory_id < game.inventory.len() {
+                let loose = game.inventory[inventory_id].equipment
+                    .map_or(false, |equipment| !equipment.equipped);
+                if loose && game.rng.gen_range(0, 100) < SWEEP_AWAY_CHANCE {
+                    // it sinks where it was dropped, and can be fished out again
+                    let mut item = game.inventory.remove(inventory_id);
+                    game.log.add(format!("The current tears the {} from your pack!", item.name),
+                                 colors::LIGHT_BLUE);
+                    item.set_pos(x, y);
+                    objects.spawn(item);
+                } else {
+                    inventory_id += 1;
+                }
+            }
+            false
+        }
+        Some(TileEffect::Burn(damage)) => {
+            game.log.add(format!("The lava burns you for {} hit points!", damage), colors::ORANGE);
+            objects[PLAYER].take_damage(damage, "lava", game);
+            false
+        }
+        Some(TileEffect::Fall) => {
+            game.log.add("You fall down the chasm!", colors::RED);
+            objects[PLAYER].take_damage(FALL_DAMAGE, "fall", game);
+            if objects[PLAYER].alive {
+                fall_to_next_level(objects, game, fov, templates);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Whatever a monster just walked onto does its thing to it
+pub fn monster_enters_tile(id: EntityId, objects: &mut Entities, game: &mut Game) {
+    let (x, y) = objects[id].pos();
+    match game.map[x as usize][y as usize].kind.effect() {
+        Some(TileEffect::Burn(damage)) => {
+            game.log.add(format!("The lava burns the {} for {} hit points!", objects[id].name, damage),
+                         colors::ORANGE);
+            // leading a monster into lava counts as killing it
+            if let Some(xp) = objects[id].take_damage(damage, "lava", game) {
+                objects[PLAYER].fighter.as_mut().unwrap().xp += xp;
+            }
+        }
+        Some(TileEffect::Fall) => {
+            game.log.add(format!("The {} falls down the chasm!", objects[id].name), colors::LIGHT_GREY);
+            objects.despawn(id);
+        }
+        // monsters don't carry anything to lose
+        Some(TileEffect::SweepAway) | None => {}
+    }
+}
@@ -0,0 +1,134 @@
+use rand::Rng;
+use rand::distributions::{Weighted, WeightedChoice, IndependentSample};
+use crate::types::colors;
+
+use crate::types::*;
+use crate::r#const::*;
+use super::*;
+
+/// Hide a random number of traps in an area's floor, depending on the level
+pub fn place_traps(area: &Area, map: &mut Map, level: u32, rng: &mut GameRng) {
+    // maximum number of traps per area
+    let max_traps = from_dungeon_level(&[
+                                       Transition {level: 1, value: 1},
+                                       Transition {level: 5, value: 2},
+    ], level);
+
+    // choose random number of traps
+    let num_traps = rng.gen_range(0, max_traps + 1);
+
+    for _ in 0..num_traps {
+        // trap random table
+        let trap_chances = &mut [
+            Weighted {weight: 100, item: None},
+            Weighted {weight: from_dungeon_level(&[Transition {level: 1, value: 30}], level),
+                      item: Some(TrapKind::Dart)},
+            Weighted {weight: from_dungeon_level(&[Transition {level: 2, value: 15}], level),
+                      item: Some(TrapKind::Alarm)},
+            Weighted {weight: from_dungeon_level(&[Transition {level: 3, value: 15}], level),
+                      item: Some(TrapKind::Teleport)},
+            Weighted {weight: from_dungeon_level(&[Transition {level: 4, value: 10},
+                                                   Transition {level: 7, value: 20}], level),
+                      item: Some(TrapKind::Trapdoor)},
+        ];
+        let trap_choice = WeightedChoice::new(trap_chances);
+        let kind = match trap_choice.ind_sample(rng) {
+            Some(kind) => kind,
+            None => continue,
+        };
+
+        // only on plain floor, out of doorways
+        let (x, y) = area.random_tile(rng);
+        let tile = &mut map[x as usize][y as usize];
+        if tile.kind == TileKind::Floor && !tile.blocked && tile.door.is_none() && tile.trap.is_none() {
+            tile.trap = Some(Trap { kind, hidden: true });
+        }
+    }
+}
+
+/// Set off any trap under whoever just moved, the player or a monster.
+/// Returns whether they left the level.
+pub fn spring_trap(id: EntityId, objects: &mut Entities, game: &mut Game, fov: &mut FovMap,
+                   templates: &Templates) -> bool {
+    let (x, y) = objects[id].pos();
+    let kind = match game.map[x as usize][y as usize].trap {
+        Some(trap) => trap.kind,
+        None => return false,
+    };
+    // a trap going off where the player can see it doesn't stay hidden
+    let seen = id == PLAYER || fov.is_in_fov(x, y);
+    if seen {
+        game.map[x as usize][y as usize].trap = Some(Trap { kind, hidden: false });
+    }
+    let name = if id == PLAYER { "you".to_string() } else { format!("the {}", objects[id].name) };
+
+    match kind {
+        TrapKind::Dart => {
+            if seen {
+                game.log.add(format!("A dart shoots out and hits {} for {} hit points!", name, DART_DAMAGE),
+                             colors::ORANGE);
+            }
+            if let Some(xp) = objects[id].take_damage(DART_DAMAGE, "dart trap", game) {
+                // leading a monster over a trap counts as killing it
+                if id != PLAYER {
+                    objects[PLAYER].fighter.as_mut().unwrap().xp += xp;
+                }
+            }
+            false
+        }
+        TrapKind::Teleport => {
+            // anywhere on the level that can be walked to from here
+            let landing: Vec<_> = connected_region(&game.map, (x, y), Tile::passable).into_iter()
+                .filter(|&(x, y)| {
+                    let tile = &game.map[x as usize][y as usize];
+                    tile.kind == TileKind::Floor && tile.door.is_none() && tile.trap.is_none() &&
+                        !is_blocked(x, y, &game.map, objects)
+                })
+                .collect();
+            if seen {
+                game.log.add(format!("A flash of light, and {} vanish{}!", name,
+                                     if id == PLAYER { "" } else { "es" }),
+                             colors::LIGHT_MAGENTA);
+            }
+            if let Some(&(x, y)) = game.rng.choose(&landing) {
+                objects[id].set_pos(x, y);
+            }
+            false
+        }
+        TrapKind::Alarm => {
+            // heard all over the level, seen or not
+            game.log.add("A loud alarm rings out across the level!", colors::LIGHT_YELLOW);
+            let monsters: Vec<_> = objects.with_ai().map(|(id, _)| id).collect();
+            for monster_id in monsters {
+                let ai = objects[monster_id].ai.take().map(alert);
+                objects[monster_id].ai = ai;
+            }
+            false
+        }
+        TrapKind::Trapdoor => {
+            if id == PLAYER {
+                game.log.add("A trapdoor opens under you!", colors::RED);
+                fall_to_next_level(objects, game, fov, templates);
+                true
+            } else {
+                if seen {
+                    game.log.add(format!("A trapdoor opens under {}!", name), colors::LIGHT_GREY);
+                }
+                objects.despawn(id);
+                false
+            }
+        }
+    }
+}
+
+/// a monster's AI once it's heard the alarm
+fn alert(ai: Ai) -> Ai {
+    match ai {
+        Ai::Basic => Ai::Alerted,
+        // still confused, but it'll come looking once it gets over it
+        Ai::Confused { previous_ai, num_turns } => {
+            Ai::Confused { previous_ai: Box::new(alert(*previous_ai)), num_turns }
+        }
+        Ai::Alerted => Ai::Alerted,
+    }
+}
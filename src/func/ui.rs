@@ -1,5 +1,5 @@
 use super::*;
-use tcod::colors::{self, Color};
+use crate::types::colors::{self, Color};
 use crate::r#const::*;
 use crate::types::object::Object;
 use crate::types::slot::Slot;
@@ -11,52 +11,87 @@ use rand::Rng;
 use rand::distributions::{Weighted, WeightedChoice, IndependentSample};
 
 use std::cmp;
+use std::time::SystemTime;
+
+/// Split text into lines no wider than `width`, breaking between words.
+/// Newlines in the text always start a new line.
+pub fn wrap_text(text: &str, width: i32) -> Vec<String> {
+    let width = cmp::max(width, 1) as usize;
+    let mut lines = vec![];
+    if text.is_empty() {
+        return lines;
+    }
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let line_length = line.chars().count();
+            if line_length > 0 && line_length + 1 + word.chars().count() > width {
+                lines.push(line);
+                line = String::new();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+            // a word too long for a whole line gets split up
+            while line.chars().count() > width {
+                let rest: String = line.chars().skip(width).collect();
+                line = line.chars().take(width).collect();
+                lines.push(line);
+                line = rest;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
 
-use tcod::console::*;
-use tcod::map::{Map as FovMap, FovAlgorithm};
+/// print a line of text centered on `x`
+pub fn print_centered(renderer: &mut dyn Renderer, x: i32, y: i32, text: &str, color: Color) {
+    let length = text.chars().count() as i32;
+    renderer.print(x - length / 2, y, text, color);
+}
 
-use tcod::input::Key;
-use tcod::input::{self, Event, Mouse};
-use tcod::input::KeyCode::*;
+/// blank out a box in the middle of the screen to draw a window in, returning
+/// its top left corner
+fn open_window(renderer: &mut dyn Renderer, width: i32, height: i32) -> (i32, i32) {
+    let x = SCREEN_WIDTH / 2 - width / 2;
+    let y = SCREEN_HEIGHT / 2 - height / 2;
+    let blank = " ".repeat(width as usize);
+    renderer.fill(x, y, width, height, colors::BLACK);
+    for row in 0..height {
+        renderer.print(x, y + row, &blank, colors::WHITE);
+    }
+    (x, y)
+}
 
 pub fn menu<T: AsRef<str>>(header: &str, options: &[T], width: i32,
-                       root: &mut Root) -> Option<usize> {
+                       renderer: &mut dyn Renderer) -> Option<usize> {
     assert!(options.len() <= 26, "Cannot have a menu with more than 26 options.");
 
     // calculate total height for the header (after auto-wrap) and one line per option
-    let header_height = root.get_height_rect(0, 0, width, SCREEN_HEIGHT, header);
+    let header_lines = wrap_text(header, width);
+    let header_height = header_lines.len() as i32;
     let height = options.len() as i32 + header_height;
 
-    // create an off-screen console that represents the menu's window
-    let mut window = Offscreen::new(width, height);
+    // draw the menu's window over whatever is on the screen
+    let (x, y) = open_window(renderer, width, height);
 
     // print the header, with auto-wrap
-    window.set_default_foreground(colors::WHITE);
-    window.print_rect_ex(0, 0, width, height, BackgroundFlag::None, TextAlignment::Left, header);
-
-    // calculate total height for the header (after auto-wrap) and one line per option
-    let header_height = if header.is_empty() {
-        0
-    } else {
-        root.get_height_rect(0, 0, width, SCREEN_HEIGHT, header)
-    };
+    for (line_number, line) in header_lines.iter().enumerate() {
+        renderer.print(x, y + line_number as i32, line, colors::WHITE);
+    }
 
     // print all the options
     for (index, option_text) in options.iter().enumerate() {
         let menu_letter = (b'a' + index as u8) as char;
         let text = format!("({}) {}", menu_letter, option_text.as_ref());
-        window.print_ex(0, header_height + index as i32,
-                        BackgroundFlag::None, TextAlignment::Left, text);
+        renderer.print(x, y + header_height + index as i32, &text, colors::WHITE);
     }
 
-    // blit the contents of "window" to the root console
-    let x = SCREEN_WIDTH / 2 - width / 2;
-    let y = SCREEN_HEIGHT / 2 - height / 2;
-    tcod::console::blit(&mut window, (0, 0), (width, height), root, (x, y), 1.0, 0.7);
-
-    // present the root console to the player and wait for a key-press
-    root.flush();
-    let key = root.wait_for_keypress(true);
+    // present the screen to the player and wait for a key-press
+    renderer.flush();
+    let key = renderer.wait_for_key();
 
     // convert the ASCII code to an index; if it corresponds to an option, return it
     if key.printable.is_alphabetic() {
@@ -72,7 +107,7 @@ pub fn menu<T: AsRef<str>>(header: &str, options: &[T], width: i32,
 
 }
 
-pub fn inventory_menu(inventory: &[Object], header: &str, root: &mut Root) -> Option<usize> {
+pub fn inventory_menu(inventory: &[Object], header: &str, renderer: &mut dyn Renderer) -> Option<usize> {
     // how a menu with each item of the inventory as an option
     let options = if inventory.len() == 0 {
         vec!["Inventory is empty.".into()]
@@ -88,7 +123,7 @@ pub fn inventory_menu(inventory: &[Object], header: &str, root: &mut Root) -> Op
         }).collect()
     };
 
-    let inventory_index = menu(header, &options, INVENTORY_WIDTH, root);
+    let inventory_index = menu(header, &options, INVENTORY_WIDTH, renderer);
 
     // if an item was chosen, return it
     if inventory.len() > 0 {
@@ -100,15 +135,124 @@ pub fn inventory_menu(inventory: &[Object], header: &str, root: &mut Root) -> Op
 
 
 
-/// return a string with the names of all objects under the mouse
-pub fn get_names_under_mouse(mouse: Mouse, objects: &[Object], fov_map: &FovMap) -> String {
-    let (x, y) = (mouse.cx as i32, mouse.cy as i32);
+/// returns the position of a chosen monster inside FOV up to a range, or None if cancelled
+pub fn target_monster(renderer: &mut dyn Renderer,
+                  state: &GameState,
+                  keymap: &Keymap,
+                  max_range: Option<f32>)
+    -> Option<(i32, i32)> {
+        loop {
+            match target_tile(renderer, state, keymap, max_range) {
+                Some((x, y)) => {
+                    // return the first chosen monster, otherwise continue looping
+                    if monster_at(x, y, max_range, &state.objects, &state.fov).is_some() {
+                        return Some((x, y))
+                    }
+                }
+                None => return None,
+            }
+        }
+    }
+
+/// Return the position of a tile in the player's FOV (optionally in a range),
+/// or None if cancelled. The cursor moves with the movement keys or the mouse,
+/// and Enter or a left-click picks the tile under it. Escape or a right-click
+/// cancels.
+pub fn target_tile(renderer: &mut dyn Renderer,
+               state: &GameState,
+               keymap: &Keymap,
+               max_range: Option<f32>)
+    -> Option<(i32, i32)> {
+        let mut cursor = state.objects[PLAYER].pos();
+        while !renderer.is_closed() {
+            // render the screen. this erases the inventory and shows the names of
+            // objects under the cursor.
+            render_all(renderer, state, cursor);
+            renderer.fill(cursor.0, cursor.1, 1, 1, colors::LIGHT_GREY);
+            renderer.flush();
+
+            let mut chosen = false;
+            match renderer.poll_input() {
+                Some(Input::Mouse(m)) => {
+                    cursor = (m.cx, m.cy);
+                    if m.rbutton_pressed {
+                        return None;  // cancel if the player right-clicked
+                    }
+                    chosen = m.lbutton_pressed;
+                }
+                Some(Input::Key(key)) => {
+                    match key.code {
+                        Enter | NumPadEnter => chosen = true,
+                        Escape => return None,
+                        _ => {
+                            if let Some((dx, dy)) = keymap.action_for(key).and_then(|a| a.direction()) {
+                                cursor = (cmp::min(cmp::max(cursor.0 + dx, 0), MAP_WIDTH - 1),
+                                          cmp::min(cmp::max(cursor.1 + dy, 0), MAP_HEIGHT - 1));
+                            }
+                        }
+                    }
+                }
+                None => {}
+            }
+
+            // accept the target if it's in FOV, and in case a range is
+            // specified, if it's in that range
+            let (x, y) = cursor;
+            let in_fov = (x < MAP_WIDTH) && (y < MAP_HEIGHT) && state.fov.is_in_fov(x, y);
+            let in_range = max_range.map_or(
+                true, |range| state.objects[PLAYER].distance(x, y) <= range);
+            if chosen && in_fov && in_range {
+                return Some((x, y))
+            }
+        }
+        None
+    }
+
+/// ask the player for whatever target an inventory item needs
+pub fn target_for_item(inventory_id: usize, renderer: &mut dyn Renderer, state: &mut GameState,
+                       keymap: &Keymap) -> Option<(i32, i32)> {
+    let name = state.game.inventory[inventory_id].name.clone();
+    match state.game.inventory[inventory_id].item.map_or(Targeting::None, |item| item.targeting()) {
+        Targeting::None => None,
+        Targeting::Tile => {
+            state.game.log.add(format!("Pick a target tile for the {} and press Enter or left-click, \
+                                        Escape cancels.", name), colors::LIGHT_CYAN);
+            target_tile(renderer, state, keymap, None)
+        }
+        Targeting::Monster { max_range } => {
+            state.game.log.add(format!("Pick an enemy for the {} and press Enter or left-click, \
+                                        Escape cancels.", name), colors::LIGHT_CYAN);
+            target_monster(renderer, state, keymap, Some(max_range))
+        }
+    }
+}
+
+/// keep asking the player which stat to raise until they pick one
+pub fn level_up_menu(player: &Object, renderer: &mut dyn Renderer) -> Stat {
+    let fighter = player.fighter.unwrap();
+    loop {
+        let choice = menu(
+            "Level up! Choose a stat to raise:\n",
+            &[format!("Constitution (+20 HP, from {})", fighter.base_max_hp),
+            format!("Strength (+1 attack, from {})", fighter.base_power),
+            format!("Agility (+1 defense, better searching, from {})", fighter.base_defense)],
+            LEVEL_SCREEN_WIDTH, renderer);
+        match choice {
+            Some(0) => return Stat::Constitution,
+            Some(1) => return Stat::Strength,
+            Some(2) => return Stat::Agility,
+            _ => {}  // keep asking until a choice is made
+        }
+    }
+}
 
-    // create a list with the names of all objects at the mouse's coordinates and in FOV
+/// return a string with the names of all objects at a position
+pub fn get_names_at(x: i32, y: i32, objects: &Entities, fov_map: &FovMap) -> String {
+    // create a list with the names of all objects at the coordinates and in FOV
     let names = objects
         .iter()
-        .filter(|obj| {obj.pos() == (x, y) && fov_map.is_in_fov(obj.x, obj.y)})
-        .map(|obj| obj.name.clone())
+        .filter(|&(_, obj)| {obj.pos() == (x, y) && fov_map.is_in_fov(obj.x, obj.y)})
+        .map(|(_, obj)| obj.name.clone())
         .collect::<Vec<_>>();
 
     names.join(", ")  // join the names, separated by commas
@@ -124,7 +268,7 @@ pub fn message<T: Into<String>>(messages: &mut Messages, message: T, color: Colo
 }
 
 
-pub fn render_bar(panel: &mut Offscreen,
+pub fn render_bar(renderer: &mut dyn Renderer,
               x: i32,
               y: i32,
               total_width: i32,
@@ -138,24 +282,77 @@ pub fn render_bar(panel: &mut Offscreen,
     let bar_width = (value as f32 / maximum as f32 * total_width as f32) as i32;
 
     // render the background first
-    panel.set_default_background(back_color);
-    panel.rect(x, y, total_width, 1, false, BackgroundFlag::Screen);
+    renderer.fill(x, y, total_width, 1, back_color);
 
     // now render the bar on top
-    panel.set_default_background(bar_color);
     if bar_width > 0 {
-        panel.rect(x, y, bar_width, 1, false, BackgroundFlag::Screen);
+        renderer.fill(x, y, bar_width, 1, bar_color);
     }
 
     // finally, some centered text with the values
-    panel.set_default_foreground(colors::WHITE);
-    panel.print_ex(x + total_width / 2, y, BackgroundFlag::None, TextAlignment::Center,
-                   &format!("{}: {}/{}", name, value, maximum));
+    print_centered(renderer, x + total_width / 2, y, &format!("{}: {}/{}", name, value, maximum),
+                   colors::WHITE);
+}
+
+/// Ask the player to press a movement key, returning which way it goes, or
+/// None for any other key
+pub fn ask_direction(prompt: &str, renderer: &mut dyn Renderer, keymap: &Keymap) -> Option<(i32, i32)> {
+    let width = prompt.chars().count() as i32 + 2;
+    let (x, y) = open_window(renderer, width, 1);
+    renderer.print(x + 1, y, prompt, colors::WHITE);
+    renderer.flush();
+    let key = renderer.wait_for_key();
+    keymap.action_for(key).and_then(Action::direction)
 }
 
-pub fn msgbox(text: &str, width: i32, root: &mut Root) {
+pub fn msgbox(text: &str, width: i32, renderer: &mut dyn Renderer) {
     let options: &[&str] = &[];
-    menu(text, options, width, root);
+    menu(text, options, width, renderer);
+}
+
+/// Ask the player to type something in, e.g. their name. Returns None if they
+/// press Escape.
+pub fn text_input(header: &str, max_length: usize, width: i32, renderer: &mut dyn Renderer) -> Option<String> {
+    let mut text = String::new();
+    let header_lines = wrap_text(header, width);
+    let header_height = header_lines.len() as i32;
+    let height = header_height + 1;
+
+    while !renderer.is_closed() {
+        let (x, y) = open_window(renderer, width, height);
+        for (line_number, line) in header_lines.iter().enumerate() {
+            renderer.print(x, y + line_number as i32, line, colors::WHITE);
+        }
+        renderer.print(x, y + header_height, &format!("> {}_", text), colors::WHITE);
+        renderer.flush();
+
+        let key = renderer.wait_for_key();
+        match key.code {
+            Enter if !text.trim().is_empty() => return Some(text.trim().to_string()),
+            Escape => return None,
+            Backspace => { text.pop(); }
+            _ => {
+                let c = key.printable;
+                let allowed = c.is_alphanumeric() || c == ' ' || c == '-' || c == '\'';
+                if allowed && text.chars().count() < max_length {
+                    text.push(c);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// e.g. "5 minutes ago", for showing when a game was last played
+pub fn time_ago(time: SystemTime) -> String {
+    let seconds = SystemTime::now().duration_since(time).map(|d| d.as_secs()).unwrap_or(0);
+    let (amount, unit) = match seconds {
+        0..=59 => return "just now".into(),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    format!("{} {}{} ago", amount, unit, if amount == 1 { "" } else { "s" })
 }
 
 pub fn create_room(room: Rect, map: &mut Map) {
@@ -178,3 +375,18 @@ pub fn create_v_tunnel(y1: i32, y2: i32, x: i32, map: &mut Map) {
     }
 }
 
+/// join two points with an L-shaped tunnel, turning the corner one way or the other at random
+pub fn create_l_tunnel(from: (i32, i32), to: (i32, i32), rng: &mut GameRng, map: &mut Map) {
+    let ((from_x, from_y), (to_x, to_y)) = (from, to);
+    // toss a coin (random bool value -- either true or false)
+    if rng.gen() {
+        // first move horizontally, then vertically
+        create_h_tunnel(from_x, to_x, from_y, map);
+        create_v_tunnel(from_y, to_y, to_x, map);
+    } else {
+        // first move vertically, then horizontally
+        create_v_tunnel(from_y, to_y, from_x, map);
+        create_h_tunnel(from_x, to_x, to_y, map);
+    }
+}
+
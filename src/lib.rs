@@ -1,9 +1,12 @@
 #![feature(uniform_paths)]
+#[cfg(feature = "tcod")]
 extern crate tcod;
 extern crate rand;
 extern crate serde;
 #[macro_use] extern crate serde_derive;
-extern crate serde_json;
+#[macro_use] extern crate serde_json;
+extern crate flate2;
+extern crate libc;
 
 pub mod loot;
 pub mod types;
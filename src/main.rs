@@ -4,7 +4,6 @@
  * CRATES/USE calls
  */
 
-extern crate tcod;
 extern crate rand;
 extern crate serde;
 #[macro_use]
@@ -20,12 +19,6 @@ use rand::distributions::{Weighted, WeightedChoice, IndependentSample};
 
 use std::cmp;
 
-use tcod::console::*;
-use tcod::colors::{self, Color};
-use tcod::map::{Map as FovMap, FovAlgorithm};
-
-use tcod::input::KeyCode::*;
-
 use rouge::types::*;
 use rouge::types::object::Object;
 use rouge::types::deathcallback::DeathCallback;
@@ -41,27 +34,93 @@ use rouge::func::*;
 
 fn main(){
 
-    // Init the root window here. All other settings fallback to default
-    let root = Root::initializer()
-        .font("./fonts/DarkondDigsDeeper_16x16.png", FontLayout::AsciiInRow)
-        .font_type(FontType::Default)
-        .font_dimensions(16,16)
-        .size(SCREEN_WIDTH, SCREEN_HEIGHT)
-        .title("Rouge")
-        .init();
-
-    // Limit FPS here
-    tcod::system::set_fps(LIMIT_FPS);
-
-    let mut tcod = Tcod {
-        root: root,
-        con: Offscreen::new(MAP_WIDTH, MAP_HEIGHT),
-        panel: Offscreen::new(SCREEN_WIDTH, PANEL_HEIGHT),
-        fov: FovMap::new(MAP_WIDTH, MAP_HEIGHT),
-        mouse: Default::default(),
+    // `--seed <n>` starts new games from a fixed seed, to reproduce a dungeon
+    let args: Vec<String> = std::env::args().collect();
+    let arg_value = |name: &str| {
+        args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1))
+    };
+    let seed = arg_value("--seed")
+        .map(|seed| seed.parse::<u64>().expect("--seed needs a number"));
+
+    // monster definitions and other game data
+    let templates = load_templates().expect("Game data not found");
+    let mut settings = load_settings().unwrap_or_else(|e| {
+        eprintln!("{}, using the default settings", e);
+        Settings::default()
+    });
+
+    // `--bot` plays over stdin and stdout instead, for automated players
+    if args.iter().any(|arg| arg == "--bot") {
+        let mut state = GameState::new(seed.unwrap_or_else(rand::random), templates);
+        if let Err(e) = run_bot(&mut state) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // `--replay <file>` plays a recorded game back, `--headless` just checks it
+    let replay = arg_value("--replay")
+        .map(|path| load_replay(path).expect("Couldn't read the replay"));
+    if let Some(ref replay) = replay {
+        if args.iter().any(|arg| arg == "--headless") {
+            match run_replay(replay, &templates) {
+                Ok(state) => println!("Replay of seed {} matches: {} commands, dungeon level {}.",
+                                      replay.seed, replay.commands.len(), state.game.dungeon_level),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+    }
+
+    // Open the window, with the font from the settings if it's still around,
+    // or take over the terminal with `--terminal`
+    let fonts = load_fonts().expect("Font list not found");
+    let mut renderer = if args.iter().any(|arg| arg == "--terminal") {
+        terminal_renderer()
+    } else {
+        window_renderer(&fonts, &settings)
     };
 
-    main_menu(&mut tcod);
+    match replay {
+        Some(replay) => watch_replay(&mut *renderer, &replay, &templates),
+        None => main_menu(&mut *renderer, &templates, &fonts, &mut settings, seed),
+    }
     // let (mut objects, mut game) = new_game(&mut tcod);
     // play_game(&mut objects, &mut game, &mut tcod);
 }
+
+#[cfg(feature = "tcod")]
+fn window_renderer(fonts: &[FontInfo], settings: &Settings) -> Box<dyn Renderer> {
+    let font = settings.font.as_ref()
+        .and_then(|name| fonts.iter().find(|font| &font.name == name))
+        .unwrap_or(&fonts[0])
+        .clone();
+    Box::new(TcodRenderer::new(font))
+}
+
+/// built without a window, so the terminal is all there is
+#[cfg(not(feature = "tcod"))]
+fn window_renderer(_fonts: &[FontInfo], _settings: &Settings) -> Box<dyn Renderer> {
+    terminal_renderer()
+}
+
+#[cfg(unix)]
+fn terminal_renderer() -> Box<dyn Renderer> {
+    match TerminalRenderer::new() {
+        Ok(renderer) => Box::new(renderer),
+        Err(e) => {
+            eprintln!("Couldn't set up the terminal: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn terminal_renderer() -> Box<dyn Renderer> {
+    eprintln!("--terminal is only supported on Unix-like systems");
+    std::process::exit(1);
+}
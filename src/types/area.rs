@@ -0,0 +1,54 @@
+use super::*;
+
+/// A part of a level that gets its own monsters and items: a room, or a
+/// stretch of cave. Any shape, as long as every tile in it is floor.
+#[derive(Clone, Debug)]
+pub struct Area {
+    pub tiles: Vec<(i32, i32)>,
+    center: (i32, i32),
+}
+
+impl Area {
+    /// an area made of the given floor tiles, which mustn't be empty
+    pub fn new(tiles: Vec<(i32, i32)>) -> Self {
+        assert!(!tiles.is_empty(), "An area needs at least one tile.");
+        // the tile closest to the middle, so the center is always floor
+        let count = tiles.len() as f32;
+        let mean_x = tiles.iter().map(|&(x, _)| x as f32).sum::<f32>() / count;
+        let mean_y = tiles.iter().map(|&(_, y)| y as f32).sum::<f32>() / count;
+        let distance = |&(x, y): &(i32, i32)| (x as f32 - mean_x).powi(2) + (y as f32 - mean_y).powi(2);
+        let center = *tiles.iter()
+            .min_by(|a, b| distance(a).partial_cmp(&distance(b)).unwrap())
+            .unwrap();
+        Area { tiles, center }
+    }
+
+    pub fn center(&self) -> (i32, i32) {
+        self.center
+    }
+
+    /// the tile farthest (in a straight line) from the given spot
+    pub fn farthest_from(&self, (x, y): (i32, i32)) -> (i32, i32) {
+        *self.tiles.iter()
+            .max_by_key(|&&(tile_x, tile_y)| (tile_x - x).pow(2) + (tile_y - y).pow(2))
+            .unwrap()
+    }
+
+    /// pick one of the area's tiles at random
+    pub fn random_tile<R: Rng>(&self, rng: &mut R) -> (i32, i32) {
+        self.tiles[rng.gen_range(0, self.tiles.len())]
+    }
+}
+
+impl From<Rect> for Area {
+    /// the inside of a room, as carved by `create_room`
+    fn from(room: Rect) -> Self {
+        let mut tiles = vec![];
+        for x in (room.x1 + 1)..room.x2 {
+            for y in (room.y1 + 1)..room.y2 {
+                tiles.push((x, y));
+            }
+        }
+        Area { tiles, center: room.center() }
+    }
+}
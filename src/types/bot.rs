@@ -0,0 +1,92 @@
+use super::*;
+
+/// What a bot is told at the start of each turn in `--bot` mode, written to
+/// stdout as one line of JSON
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Observation {
+    /// how many commands have been given so far
+    pub turn: usize,
+    pub dungeon_level: u32,
+    pub player: PlayerStats,
+    /// every tile in the player's field of view
+    pub tiles: Vec<VisibleTile>,
+    /// everything else the player can see, monsters and items alike
+    pub objects: Vec<VisibleObject>,
+    pub inventory: Vec<InventoryEntry>,
+    /// messages logged since the last observation
+    pub log: Vec<String>,
+    /// the next command should be `level_up`
+    pub level_up_pending: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub x: i32,
+    pub y: i32,
+    pub alive: bool,
+    pub hp: i32,
+    pub max_hp: i32,
+    pub power: i32,
+    pub defense: i32,
+    pub level: i32,
+    pub xp: i32,
+    pub xp_to_level_up: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VisibleTile {
+    pub x: i32,
+    pub y: i32,
+    pub blocked: bool,
+    /// `"Open"`, `"Closed"` or `"Locked"` if it's a door (secret doors aren't
+    /// shown until they're found)
+    pub door: Option<Door>,
+    /// `"Floor"`, `"Wall"`, `"ShallowWater"`, `"DeepWater"`, `"Lava"`, `"Chasm"` or `"Rubble"`
+    pub kind: TileKind,
+    /// the trap on it, if one has been found
+    pub trap: Option<TrapKind>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VisibleObject {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    /// only for things that can fight
+    pub hp: Option<i32>,
+    pub max_hp: Option<i32>,
+    pub item: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InventoryEntry {
+    pub name: String,
+    pub equipped: bool,
+    /// what, if anything, the item needs pointing at when it's used
+    pub targeting: Targeting,
+}
+
+/// A command from a bot, read from stdin as one line of JSON, e.g.
+/// `{"move": {"dx": 1, "dy": 0}}`, `{"use": {"item": 0, "target": [10, 4]}}`
+/// or `"pickup"`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BotCommand {
+    Move { dx: i32, dy: i32 },
+    /// attack whatever is on an adjacent tile
+    Attack { x: i32, y: i32 },
+    Use { item: usize, #[serde(default)] target: Option<(i32, i32)> },
+    Drop { item: usize },
+    #[serde(rename = "pickup")]
+    PickUp,
+    Descend,
+    Ascend,
+    /// close the open door next to the player in this direction
+    Close { dx: i32, dy: i32 },
+    /// spend a turn looking for hidden traps and secret doors next to the player
+    Search,
+    Wait,
+    LevelUp { stat: Stat },
+    /// stop playing
+    Quit,
+}
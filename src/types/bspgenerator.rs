@@ -0,0 +1,71 @@
+use super::*;
+use crate::r#const::*;
+use crate::func::{create_room, create_l_tunnel};
+
+/// Splits the map in two again and again, like a binary space partition,
+/// then puts a room in each piece and joins every pair of halves. Rooms end
+/// up packed tightly side by side, for denser, more built-looking levels.
+pub struct BspGenerator {
+    /// pieces smaller than this across are never split further
+    pub min_leaf_size: i32,
+}
+
+impl Default for BspGenerator {
+    fn default() -> Self {
+        BspGenerator { min_leaf_size: ROOM_MAX_SIZE }
+    }
+}
+
+impl MapGenerator for BspGenerator {
+    fn generate(&self, rng: &mut GameRng) -> Layout {
+        let mut map = solid_map();
+        let mut rooms = vec![];
+        // leave the last row and column as wall, like the rooms generator does
+        let whole_map = Rect::new(0, 0, MAP_WIDTH - 1, MAP_HEIGHT - 1);
+        self.split(whole_map, rng, &mut map, &mut rooms);
+        add_doors(&mut map, &rooms, rng);
+        Layout { map, areas: rooms.into_iter().map(Area::from).collect() }
+    }
+}
+
+impl BspGenerator {
+    /// Fill an area with rooms, splitting it if it's big enough and joining
+    /// the two halves. Returns one of the area's rooms, for the caller to join
+    /// to the other half.
+    fn split(&self, area: Rect, rng: &mut GameRng, map: &mut Map, rooms: &mut Vec<Rect>) -> Rect {
+        let (width, height) = (area.x2 - area.x1, area.y2 - area.y1);
+        let can_split_across = width >= self.min_leaf_size * 2;
+        let can_split_down = height >= self.min_leaf_size * 2;
+
+        if !can_split_across && !can_split_down {
+            // a leaf: put a room in it, of at least the minimum size if it fits
+            let w = rng.gen_range(cmp::min(ROOM_MIN_SIZE, width), width + 1);
+            let h = rng.gen_range(cmp::min(ROOM_MIN_SIZE, height), height + 1);
+            let x = rng.gen_range(area.x1, area.x2 - w + 1);
+            let y = rng.gen_range(area.y1, area.y2 - h + 1);
+            let room = Rect::new(x, y, w, h);
+            create_room(room, map);
+            rooms.push(room);
+            return room;
+        }
+
+        // split the longer way, so pieces don't get long and thin
+        let across = match (can_split_across, can_split_down) {
+            (true, false) => true,
+            (false, true) => false,
+            _ => if width == height { rng.gen() } else { width > height },
+        };
+        let (first, second) = if across {
+            let split_x = rng.gen_range(area.x1 + self.min_leaf_size, area.x2 - self.min_leaf_size + 1);
+            (Rect { x2: split_x, ..area }, Rect { x1: split_x, ..area })
+        } else {
+            let split_y = rng.gen_range(area.y1 + self.min_leaf_size, area.y2 - self.min_leaf_size + 1);
+            (Rect { y2: split_y, ..area }, Rect { y1: split_y, ..area })
+        };
+
+        let first_room = self.split(first, rng, map, rooms);
+        let second_room = self.split(second, rng, map, rooms);
+        create_l_tunnel(first_room.center(), second_room.center(), rng, map);
+        if rng.gen() { first_room } else { second_room }
+    }
+}
@@ -0,0 +1,124 @@
+use super::*;
+use crate::r#const::*;
+
+/// Natural-looking caverns: start from random noise and smooth it with a
+/// cellular automaton, so walls clump together and the floor opens up into
+/// winding caves. Only the biggest cave is kept.
+pub struct CaveGenerator {
+    /// the chance, in percent, of each tile starting out as wall
+    pub wall_chance: u32,
+    /// how many rounds of smoothing to do
+    pub smoothing_steps: u32,
+    /// The cave is cut up into squares this big for placing monsters and
+    /// items, so they're spread all over it
+    pub area_size: i32,
+}
+
+impl Default for CaveGenerator {
+    fn default() -> Self {
+        CaveGenerator { wall_chance: 45, smoothing_steps: 5, area_size: 15 }
+    }
+}
+
+impl MapGenerator for CaveGenerator {
+    fn generate(&self, rng: &mut GameRng) -> Layout {
+        let mut map = solid_map();
+        for x in 1..(MAP_WIDTH - 1) {
+            for y in 1..(MAP_HEIGHT - 1) {
+                if rng.gen_range(0, 100) >= self.wall_chance {
+                    map[x as usize][y as usize] = Tile::empty();
+                }
+            }
+        }
+
+        for _ in 0..self.smoothing_steps {
+            map = smooth(&map);
+        }
+
+        // fill in every pocket that isn't part of the biggest cave
+        let cave = largest_region(&map);
+        let mut in_cave = vec![vec![false; MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+        for &(x, y) in &cave {
+            in_cave[x as usize][y as usize] = true;
+        }
+        for x in 0..MAP_WIDTH {
+            for y in 0..MAP_HEIGHT {
+                if !in_cave[x as usize][y as usize] {
+                    map[x as usize][y as usize] = Tile::wall();
+                }
+            }
+        }
+
+        // Cut the cave up into squares, going across the map, so the player
+        // starts at one end and the stairs are at the other. Squares with
+        // only a few tiles of cave in them are left empty.
+        let mut areas = vec![];
+        let mut x = 0;
+        while x < MAP_WIDTH {
+            let mut y = 0;
+            while y < MAP_HEIGHT {
+                let tiles: Vec<_> = cave.iter()
+                    .filter(|&&(tile_x, tile_y)| {
+                        tile_x >= x && tile_x < x + self.area_size &&
+                            tile_y >= y && tile_y < y + self.area_size
+                    })
+                    .cloned()
+                    .collect();
+                if tiles.len() as i32 >= self.area_size {
+                    areas.push(Area::new(tiles));
+                }
+                y += self.area_size;
+            }
+            x += self.area_size;
+        }
+
+        // a tiny cave still gets somewhere to start
+        if areas.is_empty() && !cave.is_empty() {
+            areas.push(Area::new(cave));
+        }
+        Layout { map, areas }
+    }
+}
+
+/// One round of the cellular automaton: a tile becomes wall if most of the
+/// 3x3 block around it is wall, otherwise floor. The edge of the map stays wall.
+fn smooth(map: &Map) -> Map {
+    let mut smoothed = solid_map();
+    for x in 1..(MAP_WIDTH - 1) {
+        for y in 1..(MAP_HEIGHT - 1) {
+            let mut walls = 0;
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    if map[(x + dx) as usize][(y + dy) as usize].blocked {
+                        walls += 1;
+                    }
+                }
+            }
+            if walls < 5 {
+                smoothed[x as usize][y as usize] = Tile::empty();
+            }
+        }
+    }
+    smoothed
+}
+
+/// the floor tiles of the biggest connected open space on the map
+fn largest_region(map: &Map) -> Vec<(i32, i32)> {
+    let mut seen = vec![vec![false; MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+    let mut largest = vec![];
+    for x in 0..MAP_WIDTH {
+        for y in 0..MAP_HEIGHT {
+            if seen[x as usize][y as usize] || map[x as usize][y as usize].blocked {
+                continue;
+            }
+            let region = connected_region(map, (x, y), |tile| !tile.blocked);
+            for &(region_x, region_y) in &region {
+                seen[region_x as usize][region_y as usize] = true;
+            }
+            if region.len() > largest.len() {
+                largest = region;
+            }
+        }
+    }
+    largest
+}
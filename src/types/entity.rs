@@ -0,0 +1,158 @@
+use super::*;
+use std::ops::{Index, IndexMut};
+
+/// A stable handle to an object on the map. Despawning an object bumps its
+/// slot's generation, so old handles stop working instead of silently
+/// pointing at whatever gets spawned in that slot next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EntityId {
+    pub(crate) index: u32,
+    pub(crate) generation: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Entry {
+    generation: u32,
+    object: Option<Object>,
+}
+
+/// All the objects on the current map, looked up by `EntityId`
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Entities {
+    entries: Vec<Entry>,
+    // indices of empty entries, ready to be reused
+    free: Vec<u32>,
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// add an object, returning its id
+    pub fn spawn(&mut self, object: Object) -> EntityId {
+        match self.free.pop() {
+            Some(index) => {
+                let entry = &mut self.entries[index as usize];
+                entry.object = Some(object);
+                EntityId { index, generation: entry.generation }
+            }
+            None => {
+                self.entries.push(Entry { generation: 0, object: Some(object) });
+                EntityId { index: self.entries.len() as u32 - 1, generation: 0 }
+            }
+        }
+    }
+
+    /// remove an object, returning it if the id was still valid
+    pub fn despawn(&mut self, id: EntityId) -> Option<Object> {
+        if !self.contains(id) {
+            return None;
+        }
+        let entry = &mut self.entries[id.index as usize];
+        entry.generation += 1;
+        self.free.push(id.index);
+        entry.object.take()
+    }
+
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&Object> {
+        self.entries.get(id.index as usize)
+            .filter(|entry| entry.generation == id.generation)
+            .and_then(|entry| entry.object.as_ref())
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Object> {
+        self.entries.get_mut(id.index as usize)
+            .filter(|entry| entry.generation == id.generation)
+            .and_then(|entry| entry.object.as_mut())
+    }
+
+    /// Returns two mutable borrows, or None if either id is invalid or they are the same
+    pub fn get_two_mut(&mut self, first: EntityId, second: EntityId)
+        -> Option<(&mut Object, &mut Object)> {
+        if first.index == second.index || !self.contains(first) || !self.contains(second) {
+            return None;
+        }
+        let (first_index, second_index) = (first.index as usize, second.index as usize);
+        let split_at_index = cmp::max(first_index, second_index);
+        let (first_slice, second_slice) = self.entries.split_at_mut(split_at_index);
+        let (first_entry, second_entry) = if first_index < second_index {
+            (&mut first_slice[first_index], &mut second_slice[0])
+        } else {
+            (&mut second_slice[0], &mut first_slice[second_index])
+        };
+        Some((first_entry.object.as_mut().unwrap(), second_entry.object.as_mut().unwrap()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &Object)> + '_ {
+        self.entries.iter().enumerate().filter_map(|(index, entry)| {
+            entry.object.as_ref().map(|object| {
+                (EntityId { index: index as u32, generation: entry.generation }, object)
+            })
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, &mut Object)> + '_ {
+        self.entries.iter_mut().enumerate().filter_map(|(index, entry)| {
+            let generation = entry.generation;
+            entry.object.as_mut().map(|object| {
+                (EntityId { index: index as u32, generation }, object)
+            })
+        })
+    }
+
+    /// a snapshot of every id, for loops that spawn or despawn as they go
+    pub fn ids(&self) -> Vec<EntityId> {
+        self.iter().map(|(id, _)| id).collect()
+    }
+
+    /// objects that can fight (the player and living monsters)
+    pub fn with_fighter(&self) -> impl Iterator<Item = (EntityId, &Object)> + '_ {
+        self.iter().filter(|&(_, object)| object.fighter.is_some())
+    }
+
+    /// objects with an AI, i.e. monsters that can act
+    pub fn with_ai(&self) -> impl Iterator<Item = (EntityId, &Object)> + '_ {
+        self.iter().filter(|&(_, object)| object.ai.is_some())
+    }
+
+    /// items lying on the map
+    pub fn with_item(&self) -> impl Iterator<Item = (EntityId, &Object)> + '_ {
+        self.iter().filter(|&(_, object)| object.item.is_some())
+    }
+
+    /// despawn every object the predicate returns false for
+    pub fn retain<F: FnMut(EntityId, &Object) -> bool>(&mut self, mut keep: F) {
+        for id in self.ids() {
+            if !keep(id, &self[id]) {
+                self.despawn(id);
+            }
+        }
+    }
+}
+
+impl Index<EntityId> for Entities {
+    type Output = Object;
+
+    fn index(&self, id: EntityId) -> &Object {
+        self.get(id).expect("no object with this id")
+    }
+}
+
+impl IndexMut<EntityId> for Entities {
+    fn index_mut(&mut self, id: EntityId) -> &mut Object {
+        self.get_mut(id).expect("no object with this id")
+    }
+}
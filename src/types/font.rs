@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use crate::r#const::{FONTS_DIR, ASCII_GLYPHS};
+
+/// How the characters are arranged in a font image, as libtcod reads them
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FontLayout {
+    AsciiInCol,
+    AsciiInRow,
+    Tcod,
+}
+
+/// Whether a font image is drawn in black and white or shades of grey
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FontType {
+    Default,
+    Greyscale,
+}
+
+/// One of the fonts in `fonts/`, as described in `data/fonts.json`.
+///
+/// The game's glyphs (the `CORPSE`, `WALL` and `FLOOR` constants and the
+/// glyphs in the monster and item data) are the characters
+/// DarkondDigsDeeper draws them with. Other fonts can draw them all in plain
+/// ASCII (`ASCII_GLYPHS`), and list replacements for any they draw differently.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FontInfo {
+    pub name: String,
+    /// image file name, in `fonts/`
+    pub file: String,
+    pub layout: FontLayout,
+    #[serde(rename = "type")]
+    pub font_type: FontType,
+    /// how many characters across and down the image is
+    pub columns: i32,
+    pub rows: i32,
+    /// draw the game's glyphs in plain ASCII, unless `glyphs` says otherwise
+    #[serde(default)]
+    pub ascii: bool,
+    #[serde(default)]
+    pub glyphs: HashMap<char, char>,
+}
+
+/// how a game glyph looks in plain ASCII, see `ASCII_GLYPHS`
+pub fn ascii_glyph(glyph: char) -> char {
+    ASCII_GLYPHS.iter()
+        .find(|&&(game_glyph, _)| game_glyph == glyph)
+        .map_or(glyph, |&(_, ascii)| ascii)
+}
+
+impl FontInfo {
+    pub fn path(&self) -> String {
+        format!("{}/{}", FONTS_DIR, self.file)
+    }
+
+    /// the character this font draws a game glyph with
+    pub fn glyph(&self, glyph: char) -> char {
+        match self.glyphs.get(&glyph) {
+            Some(&replacement) => replacement,
+            None if self.ascii => ascii_glyph(glyph),
+            None => glyph,
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use std::cmp;
+
+/// Which tiles the player can see from where they stand, worked out the way
+/// libtcod's basic field of view does it (rays cast from the player to every
+/// tile on the edge of their view), so games play out the same as they did
+/// when libtcod did the work.
+pub struct FovMap {
+    width: i32,
+    height: i32,
+    transparent: Vec<bool>,
+    in_fov: Vec<bool>,
+}
+
+impl FovMap {
+    /// a map with nothing see-through yet, and nothing in view
+    pub fn new(width: i32, height: i32) -> Self {
+        assert!(width > 0 && height > 0);
+        let size = (width * height) as usize;
+        FovMap {
+            width,
+            height,
+            transparent: vec![false; size],
+            in_fov: vec![false; size],
+        }
+    }
+
+    /// whether a tile can be seen through. (Walkability is kept by the game map.)
+    pub fn set(&mut self, x: i32, y: i32, transparent: bool) {
+        let index = self.index(x, y);
+        self.transparent[index] = transparent;
+    }
+
+    pub fn is_in_fov(&self, x: i32, y: i32) -> bool {
+        self.in_fov[self.index(x, y)]
+    }
+
+    /// Work out what can be seen from (x, y), up to `radius` tiles away (or
+    /// any distance if it's 0). With `light_walls`, the walls at the edge of
+    /// what can be seen are in view too.
+    pub fn compute_fov(&mut self, x: i32, y: i32, radius: i32, light_walls: bool) {
+        let (mut x_min, mut y_min, mut x_max, mut y_max) = (0, 0, self.width, self.height);
+        if radius > 0 {
+            x_min = cmp::max(0, x - radius);
+            y_min = cmp::max(0, y - radius);
+            x_max = cmp::min(self.width, x + radius + 1);
+            y_max = cmp::min(self.height, y + radius + 1);
+        }
+        let radius_squared = radius * radius;
+        for in_fov in &mut self.in_fov {
+            *in_fov = false;
+        }
+
+        // round the edge of the view, the way libtcod goes (the last two
+        // sides run on to the edge of the map rather than of the view)
+        for edge_x in x_min..x_max {
+            self.cast_ray(x, y, edge_x, y_min, radius_squared, light_walls);
+        }
+        for edge_y in y_min + 1..y_max {
+            self.cast_ray(x, y, x_max - 1, edge_y, radius_squared, light_walls);
+        }
+        for edge_x in (0..x_max - 1).rev() {
+            self.cast_ray(x, y, edge_x, y_max - 1, radius_squared, light_walls);
+        }
+        for edge_y in (1..y_max - 1).rev() {
+            self.cast_ray(x, y, x_min, edge_y, radius_squared, light_walls);
+        }
+
+        if light_walls {
+            // light up walls the rays only just missed, a quarter at a time
+            self.light_walls(x_min, y_min, x, y, -1, -1);
+            self.light_walls(x, y_min, x_max - 1, y, 1, -1);
+            self.light_walls(x_min, y, x, y_max - 1, -1, 1);
+            self.light_walls(x, y, x_max - 1, y_max - 1, 1, 1);
+        }
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        assert!(x >= 0 && y >= 0 && x < self.width && y < self.height,
+                "({}, {}) is off the map", x, y);
+        (x + y * self.width) as usize
+    }
+
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+    }
+
+    /// follow a line from the player out to (to_x, to_y), up to the first
+    /// thing that can't be seen through
+    fn cast_ray(&mut self, from_x: i32, from_y: i32, to_x: i32, to_y: i32, radius_squared: i32,
+                light_walls: bool) {
+        let mut line = Line::new((from_x, from_y), (to_x, to_y));
+        let (mut x, mut y) = (from_x, from_y);
+        let mut on_map = false;
+        let mut blocked = false;
+        if self.contains(x, y) {
+            on_map = true;
+            let index = self.index(x, y);
+            self.in_fov[index] = true;
+        }
+        loop {
+            // the end of the line is looked at twice, as libtcod does
+            let end = match line.step() {
+                Some((next_x, next_y)) => {
+                    x = next_x;
+                    y = next_y;
+                    false
+                }
+                None => true,
+            };
+            if radius_squared > 0 {
+                let (dx, dy) = (x - from_x, y - from_y);
+                if dx * dx + dy * dy > radius_squared {
+                    return;
+                }
+            }
+            if self.contains(x, y) {
+                on_map = true;
+                let index = self.index(x, y);
+                if !blocked && !self.transparent[index] {
+                    blocked = true;
+                } else if blocked {
+                    return;
+                }
+                if light_walls || !blocked {
+                    self.in_fov[index] = true;
+                }
+            } else if on_map {
+                return;
+            }
+            if end {
+                return;
+            }
+        }
+    }
+
+    /// for every tile in view in the rectangle, put the walls next to it on
+    /// the side away from the player (dx, dy) in view too
+    fn light_walls(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, dx: i32, dy: i32) {
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                let index = self.index(x, y);
+                if !self.in_fov[index] || !self.transparent[index] {
+                    continue;
+                }
+                let (x2, y2) = (x + dx, y + dy);
+                let in_x = x2 >= x0 && x2 <= x1;
+                let in_y = y2 >= y0 && y2 <= y1;
+                for &(wall_x, wall_y, inside) in &[(x2, y, in_x), (x, y2, in_y), (x2, y2, in_x && in_y)] {
+                    if inside && self.contains(wall_x, wall_y) {
+                        let index = self.index(wall_x, wall_y);
+                        if !self.transparent[index] {
+                            self.in_fov[index] = true;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The tiles on a line between two points, one step at a time, using
+/// libtcod's version of Bresenham's algorithm
+struct Line {
+    x: i32,
+    y: i32,
+    to_x: i32,
+    to_y: i32,
+    step_x: i32,
+    step_y: i32,
+    delta_x: i32,
+    delta_y: i32,
+    error: i32,
+}
+
+impl Line {
+    fn new((x, y): (i32, i32), (to_x, to_y): (i32, i32)) -> Self {
+        let (delta_x, delta_y) = (to_x - x, to_y - y);
+        let (step_x, step_y) = (delta_x.signum(), delta_y.signum());
+        let error = if step_x * delta_x > step_y * delta_y { step_x * delta_x } else { step_y * delta_y };
+        Line {
+            x,
+            y,
+            to_x,
+            to_y,
+            step_x,
+            step_y,
+            delta_x: delta_x * 2,
+            delta_y: delta_y * 2,
+            error,
+        }
+    }
+
+    /// the next tile along, or None once the end has been reached
+    fn step(&mut self) -> Option<(i32, i32)> {
+        if self.step_x * self.delta_x > self.step_y * self.delta_y {
+            if self.x == self.to_x {
+                return None;
+            }
+            self.x += self.step_x;
+            self.error -= self.step_y * self.delta_y;
+            if self.error < 0 {
+                self.y += self.step_y;
+                self.error += self.step_x * self.delta_x;
+            }
+        } else {
+            if self.y == self.to_y {
+                return None;
+            }
+            self.y += self.step_y;
+            self.error -= self.step_x * self.delta_x;
+            if self.error < 0 {
+                self.x += self.step_x;
+                self.error += self.step_y * self.delta_y;
+            }
+        }
+        Some((self.x, self.y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "tcod")]
+    use tcod::map::{Map as TcodMap, FovAlgorithm};
+
+    const PILLARS: &[&str] = &[
+        "...............",
+        "...............",
+        "....#.....#....",
+        "...............",
+        ".......@.......",
+        "...............",
+        "....#.....#....",
+        "...............",
+        "...............",
+    ];
+
+    const CORRIDORS: &[&str] = &[
+        "###########",
+        "#@........#",
+        "#####.#####",
+        "#####.#####",
+        "#...#.#...#",
+        "#.........#",
+        "###########",
+    ];
+
+    /// a map drawn with `#` for walls and `@` for where to look from
+    fn parse(rows: &[&str]) -> (FovMap, (i32, i32)) {
+        let mut fov = FovMap::new(rows[0].len() as i32, rows.len() as i32);
+        let mut from = (0, 0);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, tile) in row.chars().enumerate() {
+                fov.set(x as i32, y as i32, tile != '#');
+                if tile == '@' {
+                    from = (x as i32, y as i32);
+                }
+            }
+        }
+        (fov, from)
+    }
+
+    /// what can be seen from the `@`
+    fn view(rows: &[&str], radius: i32, light_walls: bool) -> FovMap {
+        let (mut fov, (x, y)) = parse(rows);
+        fov.compute_fov(x, y, radius, light_walls);
+        fov
+    }
+
+    /// Look from every tile that isn't wall, near and far, with and without
+    /// walls lit, and check each view is just what libtcod sees
+    #[cfg(feature = "tcod")]
+    fn assert_same_as_tcod(rows: &[&str]) {
+        let (mut fov, _) = parse(rows);
+        let mut tcod_map = TcodMap::new(fov.width, fov.height);
+        for x in 0..fov.width {
+            for y in 0..fov.height {
+                let transparent = fov.transparent[fov.index(x, y)];
+                tcod_map.set(x, y, transparent, transparent);
+            }
+        }
+        for from_x in 0..fov.width {
+            for from_y in 0..fov.height {
+                if !fov.transparent[fov.index(from_x, from_y)] {
+                    continue;
+                }
+                for &radius in &[0, 2, 5] {
+                    for &light_walls in &[true, false] {
+                        fov.compute_fov(from_x, from_y, radius, light_walls);
+                        tcod_map.compute_fov(from_x, from_y, radius, light_walls, FovAlgorithm::Basic);
+                        for x in 0..fov.width {
+                            for y in 0..fov.height {
+                                assert_eq!(fov.is_in_fov(x, y), tcod_map.is_in_fov(x, y),
+                                           "({}, {}) seen from ({}, {}), radius {}, light walls {}",
+                                           x, y, from_x, from_y, radius, light_walls);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "tcod")]
+    #[test]
+    fn pillars_are_seen_the_same_as_tcod() {
+        assert_same_as_tcod(PILLARS);
+    }
+
+    #[cfg(feature = "tcod")]
+    #[test]
+    fn corridors_are_seen_the_same_as_tcod() {
+        assert_same_as_tcod(CORRIDORS);
+    }
+
+    #[test]
+    fn pillars_cast_shadows() {
+        let fov = view(PILLARS, 0, true);
+        assert!(fov.is_in_fov(4, 2));
+        assert!(!fov.is_in_fov(1, 0));
+        assert!(fov.is_in_fov(7, 0));
+    }
+
+    #[test]
+    fn corridors_hide_what_is_round_the_corner() {
+        let fov = view(CORRIDORS, 0, false);
+        assert!(fov.is_in_fov(9, 1));
+        assert!(fov.is_in_fov(5, 1));
+        assert!(!fov.is_in_fov(5, 4));
+        assert!(!fov.is_in_fov(2, 5));
+    }
+
+    #[test]
+    fn walls_are_only_seen_when_lit() {
+        assert!(view(CORRIDORS, 0, true).is_in_fov(10, 1));
+        assert!(!view(CORRIDORS, 0, false).is_in_fov(10, 1));
+    }
+
+    #[test]
+    fn nothing_is_seen_past_the_radius() {
+        let fov = view(PILLARS, 3, true);
+        assert!(fov.is_in_fov(10, 4));
+        assert!(!fov.is_in_fov(11, 4));
+    }
+}
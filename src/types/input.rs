@@ -0,0 +1,94 @@
+/// A key press, in the same form whichever renderer it came from
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Key {
+    pub code: KeyCode,
+    /// the character the key types, for `KeyCode::Char` and the number keys
+    pub printable: char,
+    pub alt: bool,
+    pub ctrl: bool,
+    pub shift: bool,
+}
+
+/// Which key was pressed, named the way libtcod names them
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum KeyCode {
+    #[default]
+    NoKey,
+    Escape,
+    Backspace,
+    Tab,
+    Enter,
+    Shift,
+    Control,
+    Alt,
+    Pause,
+    CapsLock,
+    PageUp,
+    PageDown,
+    End,
+    Home,
+    Up,
+    Left,
+    Right,
+    Down,
+    PrintScreen,
+    Insert,
+    Delete,
+    LeftWin,
+    RightWin,
+    Apps,
+    // the numbers along the top of the keyboard
+    Number0,
+    Number1,
+    Number2,
+    Number3,
+    Number4,
+    Number5,
+    Number6,
+    Number7,
+    Number8,
+    Number9,
+    // and the ones on the numeric keypad
+    NumPad0,
+    NumPad1,
+    NumPad2,
+    NumPad3,
+    NumPad4,
+    NumPad5,
+    NumPad6,
+    NumPad7,
+    NumPad8,
+    NumPad9,
+    NumPadAdd,
+    NumPadSubtract,
+    NumPadDivide,
+    NumPadMultiply,
+    NumPadDecimal,
+    NumPadEnter,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    NumLock,
+    ScrollLock,
+    Spacebar,
+    /// any key that types a character, see `Key::printable`
+    Char,
+}
+
+/// Where the mouse is, in character cells, and whether a button was just clicked
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Mouse {
+    pub cx: i32,
+    pub cy: i32,
+    pub lbutton_pressed: bool,
+    pub rbutton_pressed: bool,
+}
@@ -1,3 +1,7 @@
+use super::*;
+use crate::r#const::CONFUSE_RANGE;
+
+/// What happens when an item is used
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Item {
     // Spells/Potions
@@ -5,20 +9,43 @@ pub enum Item {
     Lightning,
     Confuse,
     Fireball,
-    // Weapons
-    BronzeSword,  
-    IronSword,  
-    GreatAxe,
-    WarHammer,
-    WoodShield,  
-    IronShield,
-    Dagger,
-    // Amour
-    ClothShirt,
-    ClothPants,
-    LeatherWristGaurds,
-    LeatherHat,
-    LeatherChest,
-    LeatherKneeGaurds
+    // Weapons and armour
+    Equip,
+    /// opens a locked door, by walking into it
+    Key,
+}
+
+impl Item {
+    /// what the player has to pick before this item can be used
+    pub fn targeting(self) -> Targeting {
+        match self {
+            Item::Fireball => Targeting::Tile,
+            Item::Confuse => Targeting::Monster { max_range: CONFUSE_RANGE as f32 },
+            _ => Targeting::None,
+        }
+    }
+}
+
+/// Everything needed to spawn a kind of item, as read from `data/items.json`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ItemTemplate {
+    pub name: String,
+    pub glyph: char,
+    pub color: Color,
+    #[serde(rename = "use")]
+    pub use_effect: Item,
+    /// bonuses it gives when equipped, if it can be equipped at all
+    pub equipment: Option<Equipment>,
+    /// how likely this item is to spawn, by dungeon level
+    pub spawn_weight: Vec<Transition>,
+}
 
+impl ItemTemplate {
+    /// create an item from this template
+    pub fn spawn(&self, x: i32, y: i32) -> Object {
+        let mut item = Object::new(x, y, self.glyph, &self.name, self.color, false);
+        item.item = Some(self.use_effect);
+        item.equipment = self.equipment;
+        item
+    }
 }
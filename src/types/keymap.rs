@@ -0,0 +1,330 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error as DeError;
+use super::{Key, KeyCode};
+
+/// Everything the player can do with a key press, by the name used in `keymap.json`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveNorth,
+    MoveSouth,
+    MoveWest,
+    MoveEast,
+    MoveNorthWest,
+    MoveNorthEast,
+    MoveSouthWest,
+    MoveSouthEast,
+    Wait,
+    PickUp,
+    Inventory,
+    Drop,
+    Descend,
+    Ascend,
+    CloseDoor,
+    Search,
+    CharacterSheet,
+    Fullscreen,
+    Quit,
+}
+
+impl Action {
+    /// which way a movement action goes, as (dx, dy)
+    pub fn direction(self) -> Option<(i32, i32)> {
+        match self {
+            Action::MoveNorth => Some((0, -1)),
+            Action::MoveSouth => Some((0, 1)),
+            Action::MoveWest => Some((-1, 0)),
+            Action::MoveEast => Some((1, 0)),
+            Action::MoveNorthWest => Some((-1, -1)),
+            Action::MoveNorthEast => Some((1, -1)),
+            Action::MoveSouthWest => Some((-1, 1)),
+            Action::MoveSouthEast => Some((1, 1)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    /// the name used in `keymap.json`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = serde_json::to_value(self).map_err(|_| fmt::Error)?;
+        write!(f, "{}", name.as_str().unwrap_or("?"))
+    }
+}
+
+// names for the keys that don't type a character
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("Escape", KeyCode::Escape), ("Backspace", KeyCode::Backspace), ("Tab", KeyCode::Tab),
+    ("Enter", KeyCode::Enter), ("Space", KeyCode::Spacebar), ("Pause", KeyCode::Pause),
+    ("Insert", KeyCode::Insert), ("Delete", KeyCode::Delete),
+    ("Home", KeyCode::Home), ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp), ("PageDown", KeyCode::PageDown),
+    ("Up", KeyCode::Up), ("Down", KeyCode::Down), ("Left", KeyCode::Left), ("Right", KeyCode::Right),
+    ("NumPad0", KeyCode::NumPad0), ("NumPad1", KeyCode::NumPad1), ("NumPad2", KeyCode::NumPad2),
+    ("NumPad3", KeyCode::NumPad3), ("NumPad4", KeyCode::NumPad4), ("NumPad5", KeyCode::NumPad5),
+    ("NumPad6", KeyCode::NumPad6), ("NumPad7", KeyCode::NumPad7), ("NumPad8", KeyCode::NumPad8),
+    ("NumPad9", KeyCode::NumPad9), ("NumPadAdd", KeyCode::NumPadAdd),
+    ("NumPadSubtract", KeyCode::NumPadSubtract), ("NumPadDivide", KeyCode::NumPadDivide),
+    ("NumPadMultiply", KeyCode::NumPadMultiply), ("NumPadDecimal", KeyCode::NumPadDecimal),
+    ("NumPadEnter", KeyCode::NumPadEnter),
+    ("F1", KeyCode::F1), ("F2", KeyCode::F2), ("F3", KeyCode::F3), ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5), ("F6", KeyCode::F6), ("F7", KeyCode::F7), ("F8", KeyCode::F8),
+    ("F9", KeyCode::F9), ("F10", KeyCode::F10), ("F11", KeyCode::F11), ("F12", KeyCode::F12),
+];
+
+/// A single key, as written in `keymap.json`: either a character like "g" or
+/// "<", or a key name like "Up" or "NumPad8", optionally with "Ctrl+" or
+/// "Alt+" in front
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyBinding {
+    key: BoundKey,
+    ctrl: bool,
+    alt: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BoundKey {
+    Char(char),
+    Code(KeyCode),
+}
+
+impl KeyBinding {
+    pub fn matches(&self, key: Key) -> bool {
+        let key_matches = match self.key {
+            BoundKey::Char(c) => key.printable == c,
+            BoundKey::Code(code) => key.code == code,
+        };
+        key_matches && key.ctrl == self.ctrl && key.alt == self.alt
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut binding = KeyBinding { key: BoundKey::Code(KeyCode::NoKey), ctrl: false, alt: false };
+        let mut name = s;
+        // a lone "+" is the plus key, not a modifier
+        while name.len() > 1 {
+            if name.starts_with("Ctrl+") {
+                binding.ctrl = true;
+                name = &name["Ctrl+".len()..];
+            } else if name.starts_with("Alt+") {
+                binding.alt = true;
+                name = &name["Alt+".len()..];
+            } else {
+                break;
+            }
+        }
+        let mut chars = name.chars();
+        binding.key = match (chars.next(), chars.next()) {
+            (Some(c), None) => BoundKey::Char(c),
+            _ => match KEY_NAMES.iter().find(|&&(key_name, _)| key_name == name) {
+                Some(&(_, code)) => BoundKey::Code(code),
+                None => return Err(format!("unknown key \"{}\"", s)),
+            },
+        };
+        Ok(binding)
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        match self.key {
+            BoundKey::Char(c) => write!(f, "{}", c),
+            BoundKey::Code(code) => {
+                let name = KEY_NAMES.iter().find(|&&(_, named)| named == code)
+                    .map_or("?", |&(name, _)| name);
+                write!(f, "{}", name)
+            }
+        }
+    }
+}
+
+impl Serialize for KeyBinding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBinding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(D::Error::custom)
+    }
+}
+
+/// Which keys do what. Read from `keymap.json` in the config directory,
+/// where each action is given a list of keys.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keymap(BTreeMap<Action, Vec<KeyBinding>>);
+
+impl Keymap {
+    /// the action bound to a key, if there is one
+    pub fn action_for(&self, key: Key) -> Option<Action> {
+        self.0.iter()
+            .find(|&(_, keys)| keys.iter().any(|binding| binding.matches(key)))
+            .map(|(&action, _)| action)
+    }
+
+    /// Replace the keys for any actions in `other`, keeping the rest. A key
+    /// `other` gives to an action is taken away from whatever action had it.
+    pub fn merge(&mut self, other: Keymap) {
+        for keys in self.0.values_mut() {
+            keys.retain(|key| !other.0.values().any(|new_keys| new_keys.contains(key)));
+        }
+        self.0.extend(other.0);
+    }
+
+    /// every key bound to more than one action, with the actions it's bound to
+    pub fn duplicates(&self) -> Vec<(KeyBinding, Vec<Action>)> {
+        let mut duplicates: Vec<(KeyBinding, Vec<Action>)> = vec![];
+        for &key in self.0.values().flatten() {
+            if duplicates.iter().any(|&(duplicate, _)| duplicate == key) {
+                continue;
+            }
+            let actions: Vec<_> = self.0.iter()
+                .filter(|&(_, keys)| keys.contains(&key))
+                .map(|(&action, _)| action)
+                .collect();
+            if actions.len() > 1 {
+                duplicates.push((key, actions));
+            }
+        }
+        duplicates
+    }
+
+    /// build a keymap from key names, which must all be valid
+    fn from_names(names: &[(Action, &[&str])]) -> Self {
+        let bindings = names.iter().map(|&(action, keys)| {
+            (action, keys.iter().map(|key| key.parse().unwrap()).collect())
+        }).collect();
+        Keymap(bindings)
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use self::Action::*;
+        Keymap::from_names(&[
+            (MoveNorth, &["Up", "NumPad8"]),
+            (MoveSouth, &["Down", "NumPad2"]),
+            (MoveWest, &["Left", "NumPad4"]),
+            (MoveEast, &["Right", "NumPad6"]),
+            (MoveNorthWest, &["Home", "NumPad7"]),
+            (MoveNorthEast, &["PageUp", "NumPad9"]),
+            (MoveSouthWest, &["End", "NumPad1"]),
+            (MoveSouthEast, &["PageDown", "NumPad3"]),
+            (Wait, &["NumPad5", "."]),
+            (PickUp, &["g"]),
+            (Inventory, &["i"]),
+            (Drop, &["d"]),
+            (Descend, &["<"]),
+            (Ascend, &[">"]),
+            (CloseDoor, &["C"]),
+            (Search, &["S"]),
+            (CharacterSheet, &["c"]),
+            (Fullscreen, &["Ctrl+Enter"]),
+            (Quit, &["Escape"]),
+        ])
+    }
+}
+
+/// Sets of movement keys to pick from, for keyboards with or without a numpad
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum MovementPreset {
+    /// arrows and the numpad, with Home/End/PageUp/PageDown for diagonals
+    #[default]
+    Numpad,
+    /// hjkl, with yubn for diagonals
+    ViKeys,
+    /// WASD, with QEZC for diagonals
+    Wasd,
+}
+
+impl MovementPreset {
+    pub const ALL: &'static [MovementPreset] =
+        &[MovementPreset::Numpad, MovementPreset::ViKeys, MovementPreset::Wasd];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MovementPreset::Numpad => "Arrows and numpad",
+            MovementPreset::ViKeys => "Vi keys (hjkl yubn)",
+            MovementPreset::Wasd => "WASD and QEZC",
+        }
+    }
+
+    /// The keys this preset changes from the default keymap. The arrow keys
+    /// work in every preset.
+    pub fn keymap(self) -> Keymap {
+        use self::Action::*;
+        match self {
+            MovementPreset::Numpad => Keymap::default(),
+            MovementPreset::ViKeys => Keymap::from_names(&[
+                (MoveNorth, &["Up", "k"]),
+                (MoveSouth, &["Down", "j"]),
+                (MoveWest, &["Left", "h"]),
+                (MoveEast, &["Right", "l"]),
+                (MoveNorthWest, &["y"]),
+                (MoveNorthEast, &["u"]),
+                (MoveSouthWest, &["b"]),
+                (MoveSouthEast, &["n"]),
+                (Wait, &["."]),
+            ]),
+            // d and c are taken by movement, so drop and the character sheet move too
+            MovementPreset::Wasd => Keymap::from_names(&[
+                (MoveNorth, &["Up", "w"]),
+                (MoveSouth, &["Down", "s"]),
+                (MoveWest, &["Left", "a"]),
+                (MoveEast, &["Right", "d"]),
+                (MoveNorthWest, &["q"]),
+                (MoveNorthEast, &["e"]),
+                (MoveSouthWest, &["z"]),
+                (MoveSouthEast, &["c"]),
+                (Wait, &["x", "."]),
+                (Drop, &["r"]),
+                (CharacterSheet, &["Tab"]),
+            ]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_preset_gives_each_key_one_action() {
+        for &preset in MovementPreset::ALL {
+            let mut keymap = Keymap::default();
+            keymap.merge(preset.keymap());
+            assert_eq!(keymap.duplicates(), vec![], "{:?}", preset);
+        }
+    }
+
+    #[test]
+    fn custom_keys_take_over_from_the_preset() {
+        let mut keymap = Keymap::default();
+        keymap.merge(MovementPreset::ViKeys.keymap());
+        keymap.merge(Keymap::from_names(&[(Action::PickUp, &["h"])]));
+        assert_eq!(keymap.duplicates(), vec![]);
+        let h = Key { code: KeyCode::Char, printable: 'h', ..Key::default() };
+        assert_eq!(keymap.action_for(h), Some(Action::PickUp));
+    }
+
+    #[test]
+    fn one_key_for_two_actions_is_a_duplicate() {
+        let keymap = Keymap::from_names(&[(Action::Drop, &["d", "x"]), (Action::MoveEast, &["d"])]);
+        assert_eq!(keymap.duplicates(), vec![("d".parse().unwrap(), vec![Action::MoveEast, Action::Drop])]);
+    }
+}
@@ -0,0 +1,11 @@
+use super::*;
+
+/// A dungeon level the player has left, kept as it was so it's the same when
+/// they come back
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Level {
+    /// the map, including which tiles have been explored
+    pub map: Map,
+    /// everything on the level apart from the player
+    pub objects: Vec<Object>,
+}
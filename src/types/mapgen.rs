@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+
+use super::*;
+use crate::r#const::*;
+use super::roomsgenerator::RoomsGenerator;
+use super::bspgenerator::BspGenerator;
+use super::cavegenerator::CaveGenerator;
+
+/// The shape of a freshly generated level, before anything is put in it
+pub struct Layout {
+    pub map: Map,
+    /// The player starts in the first area and the stairs go in the last.
+    /// Monsters and items are placed area by area.
+    pub areas: Vec<Area>,
+}
+
+/// A way of laying out a dungeon level
+pub trait MapGenerator {
+    fn generate(&self, rng: &mut GameRng) -> Layout;
+}
+
+/// The map generators a level can be built with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GeneratorKind {
+    /// random rooms joined one after another by L-shaped tunnels
+    Rooms,
+    /// rooms packed into a binary space partition of the map
+    Bsp,
+    /// open caverns grown with a cellular automaton
+    Caves,
+}
+
+impl GeneratorKind {
+    pub fn generator(self) -> Box<dyn MapGenerator> {
+        match self {
+            GeneratorKind::Rooms => Box::new(RoomsGenerator),
+            GeneratorKind::Bsp => Box::new(BspGenerator::default()),
+            GeneratorKind::Caves => Box::new(CaveGenerator::default()),
+        }
+    }
+}
+
+/// a map of nothing but wall, to carve a level out of
+pub fn solid_map() -> Map {
+    vec![vec![Tile::wall(); MAP_HEIGHT as usize]; MAP_WIDTH as usize]
+}
+
+/// every tile that can be walked to from `start` (diagonals included),
+/// going only through tiles that are `passable`
+pub fn connected_region<F: Fn(&Tile) -> bool>(map: &Map, start: (i32, i32), passable: F)
+    -> Vec<(i32, i32)>
+{
+    let mut seen = vec![vec![false; MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+    let mut region = vec![];
+    let mut to_visit = vec![start];
+    seen[start.0 as usize][start.1 as usize] = true;
+    while let Some((x, y)) = to_visit.pop() {
+        region.push((x, y));
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let (next_x, next_y) = (x + dx, y + dy);
+                if next_x < 0 || next_y < 0 || next_x >= MAP_WIDTH || next_y >= MAP_HEIGHT {
+                    continue;
+                }
+                let (next_x, next_y) = (next_x as usize, next_y as usize);
+                if !seen[next_x][next_y] && passable(&map[next_x][next_y]) {
+                    seen[next_x][next_y] = true;
+                    to_visit.push((next_x as i32, next_y as i32));
+                }
+            }
+        }
+    }
+    region
+}
+
+/// Carve the shortest tunnel from `start` to the nearest tile that `is_goal`,
+/// going straight across and up or down only and never through a tile that
+/// `can_dig` rules out. The edge of the map is always left as wall. Returns
+/// false if there's no way through.
+pub fn dig_tunnel<G, D>(map: &mut Map, start: (i32, i32), is_goal: G, can_dig: D) -> bool
+    where G: Fn((i32, i32)) -> bool, D: Fn((i32, i32)) -> bool
+{
+    let mut came_from = vec![vec![None; MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+    let mut queue = VecDeque::new();
+    came_from[start.0 as usize][start.1 as usize] = Some(start);
+    queue.push_back(start);
+
+    while let Some((x, y)) = queue.pop_front() {
+        if is_goal((x, y)) {
+            // got there: dig back to where we started
+            let mut tile = (x, y);
+            while tile != start {
+                map[tile.0 as usize][tile.1 as usize] = Tile::empty();
+                tile = came_from[tile.0 as usize][tile.1 as usize].unwrap();
+            }
+            map[start.0 as usize][start.1 as usize] = Tile::empty();
+            return true;
+        }
+        for &(dx, dy) in &[(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let next = (x + dx, y + dy);
+            let on_map = next.0 > 0 && next.1 > 0 && next.0 < MAP_WIDTH - 1 && next.1 < MAP_HEIGHT - 1;
+            if on_map && can_dig(next) && came_from[next.0 as usize][next.1 as usize].is_none() {
+                came_from[next.0 as usize][next.1 as usize] = Some((x, y));
+                queue.push_back(next);
+            }
+        }
+    }
+    false
+}
+
+/// Put doors in the gaps where tunnels come into rooms: any floor tile on the
+/// edge of a room with wall either side of it along the edge. `DOOR_CHANCE`
+/// percent of gaps get a door, and `LOCKED_DOOR_CHANCE` percent of those are locked.
+pub fn add_doors(map: &mut Map, rooms: &[Rect], rng: &mut GameRng) {
+    for room in rooms {
+        let top_and_bottom = (room.x1 + 1..room.x2)
+            .flat_map(|x| vec![(x, room.y1), (x, room.y2)])
+            .map(|pos| (pos, (1, 0)));
+        let sides = (room.y1 + 1..room.y2)
+            .flat_map(|y| vec![(room.x1, y), (room.x2, y)])
+            .map(|pos| (pos, (0, 1)));
+        for ((x, y), (along_x, along_y)) in top_and_bottom.chain(sides).collect::<Vec<_>>() {
+            if x <= 0 || y <= 0 || x >= MAP_WIDTH - 1 || y >= MAP_HEIGHT - 1 {
+                continue;
+            }
+            let blocked = |x: i32, y: i32| map[x as usize][y as usize].blocked;
+            let is_gap = !blocked(x, y) && map[x as usize][y as usize].door.is_none() &&
+                blocked(x - along_x, y - along_y) && blocked(x + along_x, y + along_y) &&
+                !blocked(x - along_y, y - along_x) && !blocked(x + along_y, y + along_x);
+            if is_gap && rng.gen_range(0, 100) < DOOR_CHANCE {
+                let roll = rng.gen_range(0, 100);
+                let door = if roll < LOCKED_DOOR_CHANCE {
+                    Door::Locked
+                } else if roll < LOCKED_DOOR_CHANCE + SECRET_DOOR_CHANCE {
+                    Door::Secret
+                } else {
+                    Door::Closed
+                };
+                map[x as usize][y as usize] = Tile::door(door);
+            }
+        }
+    }
+}
@@ -7,14 +7,8 @@ use rand::Rng;
 use rand::distributions::{Weighted, WeightedChoice, IndependentSample};
 
 use std::cmp;
+use std::collections::BTreeMap;
 
-use tcod::console::*;
-use tcod::colors::{self, Color};
-use tcod::map::{Map as FovMap, FovAlgorithm};
-
-use tcod::input::Key;
-use tcod::input::{self, Event, Mouse};
-use tcod::input::KeyCode::*;
 
 // Import types
 pub mod object;
@@ -23,22 +17,75 @@ pub mod slot;
 pub mod deathcallback;
 pub mod rect;
 pub mod tile;
+pub mod state;
+pub mod rng;
+pub mod monster;
+pub mod entity;
+pub mod replay;
+pub mod save;
+pub mod settings;
+pub mod keymap;
+pub mod font;
+pub mod colors;
+pub mod fov;
+pub mod input;
+pub mod renderer;
+#[cfg(feature = "tcod")]
+pub mod tcodrenderer;
+#[cfg(unix)]
+pub mod terminal;
+pub mod bot;
+pub mod mapgen;
+pub mod roomsgenerator;
+pub mod bspgenerator;
+pub mod cavegenerator;
+pub mod area;
+pub mod prefab;
+pub mod level;
 
 
 // Export Types
 pub use self::object::Object;
-pub use self::item::Item;
+pub use self::item::{Item, ItemTemplate};
 pub use self::slot::Slot;
 pub use self::deathcallback::DeathCallback;
 pub use self::rect::Rect;
-pub use self::tile::Tile;
+pub use self::tile::{Tile, TileKind, TileEffect, Door, Trap, TrapKind};
+pub use self::state::GameState;
+pub use self::rng::GameRng;
+pub use self::monster::{MonsterTemplate, LevelScaling};
+pub use self::entity::{EntityId, Entities};
+pub use self::replay::{Replay, Outcome};
+pub use self::save::{SaveError, SaveSlot, SaveSummary, LegacyImport};
+pub use self::settings::{Settings, SaveFormat};
+pub use self::keymap::{Keymap, KeyBinding, Action, MovementPreset};
+pub use self::font::{FontInfo, FontLayout, FontType, ascii_glyph};
+pub use self::colors::Color;
+pub use self::fov::FovMap;
+pub use self::input::{Key, KeyCode, Mouse};
+pub use self::renderer::{Renderer, Input};
+#[cfg(feature = "tcod")]
+pub use self::tcodrenderer::TcodRenderer;
+#[cfg(unix)]
+pub use self::terminal::TerminalRenderer;
+pub use self::mapgen::{MapGenerator, Layout, GeneratorKind, solid_map, connected_region, dig_tunnel,
+                         add_doors};
+pub use self::roomsgenerator::RoomsGenerator;
+pub use self::bspgenerator::BspGenerator;
+pub use self::cavegenerator::CaveGenerator;
+pub use self::area::Area;
+pub use self::prefab::Prefab;
+pub use self::level::Level;
+pub use self::bot::{Observation, PlayerStats, VisibleTile, VisibleObject, InventoryEntry, BotCommand};
 
 
 // Smaller types
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum Ai {
     Basic,
     Confused{previous_ai: Box<Ai>, num_turns: i32},
+    /// heard an alarm: heads for the player wherever they are, until it sees them
+    Alerted,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -55,6 +102,62 @@ pub enum UseResult {
     Cancelled,
 }
 
+/// What an item needs to be told about before it can be used
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Targeting {
+    None,
+    Tile,
+    Monster { max_range: f32 },
+}
+
+/// Stats the player can raise when levelling up
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Stat {
+    Constitution,
+    Strength,
+    Agility,
+}
+
+/// A player command, as understood by the game core. Anything that needs a
+/// choice from the player (targets, level-up stats) is part of the command.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Command {
+    /// move in a direction, attacking whatever is in the way
+    Move { dx: i32, dy: i32 },
+    Wait,
+    PickUp,
+    UseItem { inventory_id: usize, target: Option<(i32, i32)> },
+    DropItem { inventory_id: usize },
+    Descend,
+    /// go back up to the level above, if the player is on the up stairs
+    Ascend,
+    /// close the open door next to the player in this direction
+    CloseDoor { dx: i32, dy: i32 },
+    /// close the open door next to the player, if there's just the one
+    CloseNearbyDoor,
+    /// spend a turn looking for hidden traps and secret doors next to the player
+    Search,
+    LevelUp(Stat),
+}
+
+/// Things that happened while the game core ran a command
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GameEvent {
+    /// a new line in the message log
+    Message(String, Color),
+    /// the player used up their turn, so the monsters moved
+    TurnTaken,
+    /// the player has enough experience to level up
+    LevelUpReady,
+    /// the player reached a new dungeon level
+    LevelChanged(u32),
+    /// the player was killed, by a monster (named) or something like "lava"
+    PlayerDied(String),
+    /// time the game was saved: the player just changed level, or it's been
+    /// `AUTOSAVE_TURNS` turns since the last one
+    SaveDue,
+}
+
 pub type Map = Vec<Vec<Tile>>;
 
 pub type Messages = Vec<(String, Color)>;
@@ -64,12 +167,14 @@ pub type Messages = Vec<(String, Color)>;
 /// An object that can be equipped, yielding bonuses.
 pub struct Equipment {
     pub slot: Slot,
+    #[serde(default)]
     pub equipped: bool,
     pub max_hp_bonus: i32,
     pub power_bonus: i32,
     pub defense_bonus: i32,
 }
 
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Transition {
     pub level: u32,
     pub value: u32,
@@ -91,15 +196,25 @@ pub struct Game {
     pub log: Messages,
     pub inventory: Vec<Object>,
     pub dungeon_level: u32,
+    /// the seed this game was started from, None for games carried over from
+    /// before seeds were kept, which can't be replayed
+    pub seed: Option<u64>,
+    pub rng: GameRng,
+    /// every command given so far, to write out as a replay
+    pub history: Vec<Command>,
+    /// the character's name, which is also the name of their save slot
+    pub name: String,
+    /// every other level visited so far, by depth, as it was left
+    pub levels: BTreeMap<u32, Level>,
 }
 
-// Cleaner params
-pub struct Tcod {
-    pub root: Root,
-    pub con: Offscreen,
-    pub panel: Offscreen,
-    pub fov: FovMap,
-    pub mouse: Mouse,
+/// Definitions of everything the dungeon can spawn, loaded from `data/`
+#[derive(Clone, Debug)]
+pub struct Templates {
+    pub monsters: Vec<MonsterTemplate>,
+    pub items: Vec<ItemTemplate>,
+    /// hand-drawn rooms, from `prefabs/`
+    pub prefabs: Vec<Prefab>,
 }
 
 // combat-related properties and methods (monster, player, NPC).
@@ -0,0 +1,52 @@
+use super::*;
+use crate::func::levels::from_dungeon_level;
+
+/// A stat that grows linearly with the dungeon level: `base + per_level * level`
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct LevelScaling {
+    pub base: i32,
+    pub per_level: f32,
+}
+
+impl LevelScaling {
+    pub fn at(&self, level: u32) -> i32 {
+        (self.base as f32 + self.per_level * level as f32) as i32
+    }
+}
+
+/// Everything needed to spawn a kind of monster, as read from `data/monsters.json`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MonsterTemplate {
+    pub name: String,
+    pub glyph: char,
+    pub color: Color,
+    /// maximum (and starting) HP, by dungeon level
+    pub max_hp: Vec<Transition>,
+    pub defense: LevelScaling,
+    pub power: LevelScaling,
+    /// experience the player gets for killing it
+    pub xp: LevelScaling,
+    pub ai: Ai,
+    pub on_death: DeathCallback,
+    /// how likely this monster is to spawn, by dungeon level
+    pub spawn_weight: Vec<Transition>,
+}
+
+impl MonsterTemplate {
+    /// create a monster from this template, with stats for the given dungeon level
+    pub fn spawn(&self, x: i32, y: i32, level: u32) -> Object {
+        let mut monster = Object::new(x, y, self.glyph, &self.name, self.color, true);
+        let max_hp = from_dungeon_level(&self.max_hp, level) as i32;
+        monster.fighter = Some(Fighter {
+            base_max_hp: max_hp,
+            hp: max_hp,
+            base_defense: self.defense.at(level),
+            base_power: self.power.at(level),
+            on_death: self.on_death,
+            xp: self.xp.at(level),
+        });
+        monster.ai = Some(self.ai.clone());
+        monster.alive = true;
+        monster
+    }
+}
@@ -1,6 +1,4 @@
 use super::*;
-use crate::r#const::FLOOR;
-use crate::r#const::COLOR_DARK_GROUND;
 
 
 // Object in the game
@@ -19,7 +17,9 @@ pub struct Object {
     pub always_visible: bool,
     pub level: i32,
     pub equipment: Option<Equipment>,
-
+    /// what took the last of its hit points: a monster's name, or the likes of "lava"
+    #[serde(skip)]
+    pub killed_by: Option<String>,
 }
 
 impl Object {
@@ -115,7 +115,7 @@ impl Object {
         if damage > 0 {
             // make the target take some damage
             game.log.add(format!("{} attacks {} for {} hit points.", self.name, target.name, damage), colors::WHITE);
-            if let Some(xp) = target.take_damage(damage, game) {
+            if let Some(xp) = target.take_damage(damage, &self.name, game) {
                 // yield experience to the player
                 self.fighter.as_mut().unwrap().xp += xp;
             }
@@ -124,7 +124,8 @@ impl Object {
         }
     }
 
-    pub fn take_damage(&mut self, damage: i32, game: &mut Game) -> Option<i32> {  
+    /// lose some hit points to `cause`, and die if they run out
+    pub fn take_damage(&mut self, damage: i32, cause: &str, game: &mut Game) -> Option<i32> {  
         // apply damage if possible
         if let Some(fighter) = self.fighter.as_mut() {
             if damage > 0 {
@@ -135,6 +136,7 @@ impl Object {
         if let Some(fighter) = self.fighter {
             if fighter.hp <= 0 {
                 self.alive = false;
+                self.killed_by = Some(cause.into());
                 fighter.on_death.callback(self, game);
                 return Some(fighter.xp);  
             }
@@ -170,26 +172,12 @@ impl Object {
             always_visible: false,
             equipment: None,
             level: 1,
+            killed_by: None,
         }
     }
 
-    /// set the color and then draw the character that represents this object at its position
-    pub fn draw(&self, con: &mut Console) {
-        con.set_default_foreground(self.color);
-        con.put_char(self.x, self.y, self.char, BackgroundFlag::None);
-    }
-
-    /// Erase the character that represents this object
-    pub fn clear(&self, con: &mut Console, map: &mut Map) {
-        // Black until explored then Floor tile when no longer visible
-        let explored = &mut map[self.x as usize][self.y as usize].explored;
-        if *explored {
-            con.set_default_foreground(COLOR_DARK_GROUND);
-            con.put_char(self.x, self.y, FLOOR, BackgroundFlag::None);
-        }
-        else{
-            con.set_default_foreground(colors::BLACK);
-            con.put_char(self.x, self.y, ' ', BackgroundFlag::None);
-        }
+    /// draw the character that represents this object at its position
+    pub fn draw(&self, renderer: &mut dyn Renderer) {
+        renderer.put_glyph(self.x, self.y, self.char, self.color, None);
     }
 }
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+/// A hand-drawn room, read from a text file in `prefabs/`. The file starts
+/// with `key: value` lines, then `---`, then the room itself:
+///
+/// ```text
+/// name: Treasure vault
+/// depth: 3-8
+/// chance: 20
+/// monster T: troll
+/// item !: healing potion
+/// ---
+/// #######
+/// #.!.!.#
+/// #..T..#
+/// ###+###
+/// ```
+///
+/// `#` is wall, `.` is floor, `<` is the stairs down and `+` is the way in,
+/// where a tunnel joins the room to the rest of the level. Spaces are left as
+/// whatever the level had there. Any other character has to be given a
+/// monster or item in the header, and is floor with that thing on it.
+#[derive(Clone, Debug)]
+pub struct Prefab {
+    pub name: String,
+    /// the shallowest depth it can appear on
+    pub min_level: u32,
+    /// the deepest depth it can appear on, if there is one
+    pub max_level: Option<u32>,
+    /// the chance, in percent, of it appearing on any level in that range
+    pub chance: u32,
+    /// rows of the room, top to bottom
+    pub rows: Vec<Vec<char>>,
+    /// the monster (by name) each placeholder character stands for
+    pub monsters: HashMap<char, String>,
+    /// the item (by name) each placeholder character stands for
+    pub items: HashMap<char, String>,
+}
+
+impl Prefab {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut prefab = Prefab {
+            name: String::new(),
+            min_level: 1,
+            max_level: None,
+            chance: 100,
+            rows: vec![],
+            monsters: HashMap::new(),
+            items: HashMap::new(),
+        };
+        let mut lines = text.lines();
+
+        // the header, up to ---
+        loop {
+            let line = match lines.next() {
+                Some(line) => line.trim(),
+                None => return Err("no --- line before the room".into()),
+            };
+            if line == "---" {
+                break;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            let colon = line.find(':').ok_or_else(|| format!("\"{}\" isn't a key: value line", line))?;
+            let (key, value) = (line[..colon].trim(), line[colon + 1..].trim());
+            let number = |value: &str| value.trim().parse::<u32>()
+                .map_err(|_| format!("{} needs a number, not \"{}\"", key, value));
+            let mut key_words = key.split_whitespace();
+            match (key_words.next(), key_words.next()) {
+                (Some("name"), None) => prefab.name = value.into(),
+                (Some("depth"), None) => match value.find('-') {
+                    Some(dash) => {
+                        prefab.min_level = number(&value[..dash])?;
+                        prefab.max_level = Some(number(&value[dash + 1..])?);
+                    }
+                    None => prefab.min_level = number(value)?,
+                },
+                (Some("chance"), None) => prefab.chance = number(value)?,
+                (Some("monster"), Some(symbol)) => {
+                    prefab.monsters.insert(legend_symbol(symbol)?, value.into());
+                }
+                (Some("item"), Some(symbol)) => {
+                    prefab.items.insert(legend_symbol(symbol)?, value.into());
+                }
+                _ => return Err(format!("unknown key \"{}\"", key)),
+            }
+        }
+
+        // the room
+        prefab.rows = lines.map(|line| line.trim_end().chars().collect())
+            .filter(|row: &Vec<char>| !row.is_empty())
+            .collect();
+        if prefab.rows.is_empty() {
+            return Err("the room is empty".into());
+        }
+        for &c in prefab.rows.iter().flat_map(|row| row.iter()) {
+            let known = "#.<+ ".contains(c) || prefab.monsters.contains_key(&c) ||
+                prefab.items.contains_key(&c);
+            if !known {
+                return Err(format!("'{}' isn't in the legend", c));
+            }
+        }
+        if prefab.name.is_empty() {
+            return Err("it needs a name".into());
+        }
+        if !prefab.cells().any(|(_, _, c)| c == '+') {
+            return Err("it needs a + to get in by".into());
+        }
+        Ok(prefab)
+    }
+
+    pub fn width(&self) -> i32 {
+        self.rows.iter().map(|row| row.len()).max().unwrap_or(0) as i32
+    }
+
+    pub fn height(&self) -> i32 {
+        self.rows.len() as i32
+    }
+
+    /// every character in the room with its position, relative to the top left corner
+    pub fn cells(&self) -> impl Iterator<Item = (i32, i32, char)> + '_ {
+        self.rows.iter().enumerate().flat_map(|(y, row)| {
+            row.iter().enumerate().map(move |(x, &c)| (x as i32, y as i32, c))
+        })
+    }
+
+    /// whether it can appear on this dungeon level
+    pub fn allowed_on(&self, level: u32) -> bool {
+        level >= self.min_level && self.max_level.map_or(true, |max| level <= max)
+    }
+}
+
+/// the one character a monster or item stands for in the room
+fn legend_symbol(symbol: &str) -> Result<char, String> {
+    let mut chars = symbol.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !"#.<+ ".contains(c) => Ok(c),
+        _ => Err(format!("\"{}\" can't stand for a monster or item", symbol)),
+    }
+}
@@ -0,0 +1,48 @@
+use super::{Color, FontInfo, Key, Mouse};
+
+/// Input from the player, in the same form whichever renderer it came from
+#[derive(Clone, Copy, Debug)]
+pub enum Input {
+    Key(Key),
+    Mouse(Mouse),
+}
+
+/// Something the game can be drawn on and take input from: a tcod window or
+/// a plain terminal. Everything is drawn in character cells on a
+/// `SCREEN_WIDTH` x `SCREEN_HEIGHT` grid, and shows up on `flush`.
+pub trait Renderer {
+    /// blank the whole screen, ready to draw a new frame
+    fn clear(&mut self);
+
+    /// draw one of the game's glyphs (see `FontInfo`), keeping the cell's
+    /// background unless one is given
+    fn put_glyph(&mut self, x: i32, y: i32, glyph: char, fg: Color, bg: Option<Color>);
+
+    /// write a line of text, keeping the background underneath
+    fn print(&mut self, x: i32, y: i32, text: &str, fg: Color);
+
+    /// set the background of a rectangle of cells
+    fn fill(&mut self, x: i32, y: i32, width: i32, height: i32, bg: Color);
+
+    fn flush(&mut self);
+
+    /// the next key press or mouse event, if there is one
+    fn poll_input(&mut self) -> Option<Input>;
+
+    fn wait_for_key(&mut self) -> Key;
+
+    /// true once the player has closed the window (or the terminal has gone away)
+    fn is_closed(&self) -> bool;
+
+    fn toggle_fullscreen(&mut self) {}
+
+    /// draw a picture over the whole screen, if the renderer can
+    fn draw_image(&mut self, _path: &str) {}
+
+    /// the font in use, for renderers that draw with one
+    fn font(&self) -> Option<&FontInfo> {
+        None
+    }
+
+    fn set_font(&mut self, _font: &FontInfo) {}
+}
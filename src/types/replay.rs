@@ -0,0 +1,42 @@
+use super::*;
+use crate::r#const::PLAYER;
+
+/// A recorded game: the seed it started from and every command the player
+/// gave. Stepping a new game through the commands plays it again exactly.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    pub commands: Vec<Command>,
+    /// where the game had got to when the replay was written
+    pub outcome: Outcome,
+}
+
+/// A summary of a game's state, used to check a replay ended up in the same place
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Outcome {
+    pub dungeon_level: u32,
+    pub alive: bool,
+    pub position: (i32, i32),
+    pub level: i32,
+    pub fighter: Option<Fighter>,
+    pub inventory: Vec<String>,
+    pub objects: usize,
+    /// any difference in random rolls along the way shows up here
+    pub rng: GameRng,
+}
+
+impl Outcome {
+    pub fn of(state: &GameState) -> Self {
+        let player = &state.objects[PLAYER];
+        Outcome {
+            dungeon_level: state.game.dungeon_level,
+            alive: player.alive,
+            position: player.pos(),
+            level: player.level,
+            fighter: player.fighter,
+            inventory: state.game.inventory.iter().map(|item| item.name.clone()).collect(),
+            objects: state.objects.len(),
+            rng: state.game.rng.clone(),
+        }
+    }
+}
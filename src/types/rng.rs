@@ -0,0 +1,45 @@
+use rand::{Rng, SeedableRng};
+
+/// The game's random number generator (xorshift64*).
+/// Unlike `thread_rng` it can be seeded and saved along with the game,
+/// so the same seed always gives the same dungeon.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        // run the seed through splitmix64 first, so that small or similar
+        // seeds still start from very different states (and never from zero,
+        // which xorshift can't leave)
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        GameRng { state: if z == 0 { 0x9E37_79B9_7F4A_7C15 } else { z } }
+    }
+}
+
+impl Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+impl SeedableRng<u64> for GameRng {
+    fn reseed(&mut self, seed: u64) {
+        *self = GameRng::new(seed);
+    }
+
+    fn from_seed(seed: u64) -> Self {
+        GameRng::new(seed)
+    }
+}
@@ -0,0 +1,46 @@
+use super::*;
+use crate::r#const::*;
+use crate::func::{create_room, create_l_tunnel};
+
+/// The classic layout: try `MAX_ROOMS` randomly placed rooms, throw away any
+/// that overlap, and join each room to the one before it with a tunnel
+pub struct RoomsGenerator;
+
+impl MapGenerator for RoomsGenerator {
+    fn generate(&self, rng: &mut GameRng) -> Layout {
+        // fill map with "blocked" tiles
+        let mut map = solid_map();
+        let mut rooms: Vec<Rect> = vec![];
+
+        for _ in 0..MAX_ROOMS {
+            // random width and height
+            let w = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+            let h = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+            // random position without going out of the boundaries of the map
+            let x = rng.gen_range(0, MAP_WIDTH - w);
+            let y = rng.gen_range(0, MAP_HEIGHT - h);
+
+            let new_room = Rect::new(x, y, w, h);
+
+            // run through the other rooms and see if they intersect with this one
+            let failed = rooms.iter().any(|other_room| new_room.intersects_with(other_room));
+
+            if !failed {
+                // this means there are no intersections, so this room is valid
+
+                // "paint" it to the map's tiles
+                create_room(new_room, &mut map);
+
+                // all rooms after the first are connected to the previous room with a tunnel
+                if let Some(previous_room) = rooms.last() {
+                    create_l_tunnel(previous_room.center(), new_room.center(), rng, &mut map);
+                }
+
+                // finally, append the new room to the list
+                rooms.push(new_room);
+            }
+        }
+        add_doors(&mut map, &rooms, rng);
+        Layout { map, areas: rooms.into_iter().map(Area::from).collect() }
+    }
+}
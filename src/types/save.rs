@@ -0,0 +1,84 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Why a game couldn't be saved or loaded
+#[derive(Debug)]
+pub enum SaveError {
+    /// reading or writing the save file failed
+    Io(io::Error),
+    /// the file isn't a save game we can make sense of
+    Malformed(serde_json::Error),
+    /// the save was written by a newer version of the game
+    TooNew { version: u32 },
+    /// an older save that couldn't be upgraded to the current format
+    Migration { from: u32, reason: String },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SaveError::Io(ref e) => write!(f, "Couldn't access the save file: {}", e),
+            SaveError::Malformed(ref e) => write!(f, "The save file is damaged: {}", e),
+            SaveError::TooNew { version } => {
+                write!(f, "The save file is from a newer version of the game (format {})", version)
+            }
+            SaveError::Migration { from, ref reason } => {
+                write!(f, "Couldn't upgrade the save file from format {}: {}", from, reason)
+            }
+        }
+    }
+}
+
+impl Error for SaveError {
+    fn cause(&self) -> Option<&dyn Error> {
+        match *self {
+            SaveError::Io(ref e) => Some(e),
+            SaveError::Malformed(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> Self {
+        SaveError::Malformed(e)
+    }
+}
+
+/// Enough about a save to list it in the menu, kept alongside the objects and
+/// game so listing doesn't have to build (or migrate) them. The whole file is
+/// still read through.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SaveSummary {
+    pub name: String,
+    pub level: i32,
+    pub dungeon_level: u32,
+}
+
+/// What became of a save left behind by an older version of the game
+#[derive(Debug, PartialEq)]
+pub enum LegacyImport {
+    /// it's been moved into the slot for the character with this name
+    Moved(String),
+    /// there's already a character with this name, so it was only put aside
+    SlotTaken(String),
+}
+
+/// A saved character, as listed in the main menu
+#[derive(Debug)]
+pub struct SaveSlot {
+    pub name: String,
+    pub path: PathBuf,
+    pub last_played: SystemTime,
+    /// the character's level and dungeon depth, or why the save couldn't be read
+    pub progress: Result<(i32, u32), SaveError>,
+}
@@ -0,0 +1,43 @@
+use super::{Keymap, MovementPreset};
+
+/// How save files are written. Saves in either format can always be loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SaveFormat {
+    /// plain JSON, easy to read (and edit)
+    Json,
+    /// gzip-compressed JSON, a fraction of the size
+    Compressed,
+}
+
+impl Default for SaveFormat {
+    fn default() -> Self {
+        SaveFormat::Json
+    }
+}
+
+/// The player's preferences, kept in `settings.json` in the config directory.
+/// Anything missing from the file gets its default.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub save_format: SaveFormat,
+    pub movement: MovementPreset,
+    /// name of the font to use, from `data/fonts.json`
+    pub font: Option<String>,
+    /// keys the player has changed in `keymap.json`, which win over the preset
+    #[serde(skip)]
+    pub custom_keys: Option<Keymap>,
+}
+
+impl Settings {
+    /// the keys to play with: the defaults, then the movement preset, then
+    /// the player's own changes
+    pub fn keymap(&self) -> Keymap {
+        let mut keymap = Keymap::default();
+        keymap.merge(self.movement.keymap());
+        if let Some(ref custom_keys) = self.custom_keys {
+            keymap.merge(custom_keys.clone());
+        }
+        keymap
+    }
+}
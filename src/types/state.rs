@@ -0,0 +1,197 @@
+use super::*;
+use crate::r#const::*;
+use crate::func::*;
+
+/// Everything needed to play a game, without a window or any input handling.
+/// Frontends turn input into `Command`s and feed them to `step`.
+pub struct GameState {
+    pub objects: Entities,
+    pub game: Game,
+    pub fov: FovMap,
+    pub templates: Templates,
+    /// turns taken since `step` last asked for a save
+    pub turns_since_save: u32,
+}
+
+impl GameState {
+    /// start a brand new game, the same seed always gives the same game
+    pub fn new(seed: u64, templates: Templates) -> Self {
+        let (objects, game) = new_game(seed, &templates);
+        GameState::from_parts(objects, game, templates)
+    }
+
+    /// wrap an existing (e.g. loaded) game, rebuilding its FOV map
+    pub fn from_parts(objects: Entities, game: Game, templates: Templates) -> Self {
+        let mut state = GameState {
+            objects,
+            game,
+            fov: FovMap::new(MAP_WIDTH, MAP_HEIGHT),
+            templates,
+            turns_since_save: 0,
+        };
+        initialise_fov(&state.game.map, &mut state.fov);
+        update_fov(&state.objects, &mut state.game, &mut state.fov);
+        state
+    }
+
+    pub fn player(&self) -> &Object {
+        &self.objects[PLAYER]
+    }
+
+    /// true when the player has enough experience for the next level
+    pub fn level_up_pending(&self) -> bool {
+        let player = self.player();
+        player.fighter.map_or(false, |f| f.xp >= level_up_xp(player))
+    }
+
+    /// Run a single player command. If it used up the player's turn every
+    /// monster gets to act too. Returns what happened along the way.
+    pub fn step(&mut self, command: Command) -> Vec<GameEvent> {
+        let mut events = vec![];
+        if !self.objects[PLAYER].alive {
+            return events;
+        }
+        self.game.history.push(command);
+        let log_len = self.game.log.len();
+        let dungeon_level = self.game.dungeon_level;
+
+        let player_pos = self.objects[PLAYER].pos();
+
+        let took_turn = self.run_command(command);
+
+        // wading through water and the like gives the monsters extra turns
+        let mut turns = 1;
+        let (x, y) = self.objects[PLAYER].pos();
+        if (x, y) != player_pos && self.game.dungeon_level == dungeon_level {
+            turns = self.game.map[x as usize][y as usize].kind.walk_cost();
+            let left_level = player_enters_tile(&mut self.objects, &mut self.game, &mut self.fov,
+                                                &self.templates) ||
+                spring_trap(PLAYER, &mut self.objects, &mut self.game, &mut self.fov, &self.templates);
+            // keeping an eye out on the way
+            if !left_level && self.objects[PLAYER].alive {
+                search_around(NOTICE_CHANCE, &self.objects, &mut self.game, &mut self.fov);
+            }
+        }
+        update_fov(&self.objects, &mut self.game, &mut self.fov);
+
+        // let monstars take their turn
+        if took_turn {
+            for _ in 0..turns {
+                let monsters: Vec<_> = self.objects.with_ai().map(|(id, _)| id).collect();
+                for id in monsters {
+                    // an earlier monster may have fallen down a chasm
+                    if !self.objects.contains(id) {
+                        continue;
+                    }
+                    let monster_pos = self.objects[id].pos();
+                    ai_take_turn(id, &mut self.objects, &mut self.game, &mut self.fov);
+                    if self.objects.contains(id) && self.objects[id].pos() != monster_pos {
+                        monster_enters_tile(id, &mut self.objects, &mut self.game);
+                    }
+                    if self.objects.contains(id) && self.objects[id].pos() != monster_pos {
+                        spring_trap(id, &mut self.objects, &mut self.game, &mut self.fov, &self.templates);
+                    }
+                }
+            }
+        }
+
+        events.extend(self.game.log[log_len..].iter()
+                      .map(|&(ref msg, color)| GameEvent::Message(msg.clone(), color)));
+        if took_turn {
+            events.push(GameEvent::TurnTaken);
+        }
+        if self.game.dungeon_level != dungeon_level {
+            events.push(GameEvent::LevelChanged(self.game.dungeon_level));
+        }
+        if !self.objects[PLAYER].alive {
+            let cause = self.objects[PLAYER].killed_by.clone().unwrap_or_default();
+            events.push(GameEvent::PlayerDied(cause));
+            return events;
+        }
+        if self.level_up_pending() {
+            events.push(GameEvent::LevelUpReady);
+        }
+        if took_turn {
+            self.turns_since_save += 1;
+        }
+        if self.game.dungeon_level != dungeon_level || self.turns_since_save >= AUTOSAVE_TURNS {
+            events.push(GameEvent::SaveDue);
+            self.turns_since_save = 0;
+        }
+        events
+    }
+
+    /// carry out the player's part of a command, returns whether it took a turn
+    fn run_command(&mut self, command: Command) -> bool {
+        match command {
+            Command::Move { dx, dy } => {
+                let (dx, dy) = (dx.signum(), dy.signum());
+                if (dx, dy) != (0, 0) {
+                    player_move_or_attack(dx, dy, &mut self.objects, &mut self.game, &mut self.fov);
+                }
+                true
+            }
+            Command::Wait => true,  // do nothing, i.e. wait for the monster to come to you
+            Command::PickUp => {
+                // pick up an item
+                let player_pos = self.objects[PLAYER].pos();
+                let item_id = self.objects.with_item()
+                    .find(|&(_, object)| object.pos() == player_pos)
+                    .map(|(id, _)| id);
+                if let Some(item_id) = item_id {
+                    pick_item_up(item_id, &mut self.objects, &mut self.game.inventory, &mut self.game.log);
+                }
+                false
+            }
+            Command::UseItem { inventory_id, target } => {
+                if inventory_id < self.game.inventory.len() {
+                    use_item(inventory_id, target, &mut self.objects, &mut self.game, &self.fov);
+                }
+                false
+            }
+            Command::DropItem { inventory_id } => {
+                if inventory_id < self.game.inventory.len() {
+                    drop_item(inventory_id, &mut self.game.inventory, &mut self.objects, &mut self.game.log);
+                }
+                false
+            }
+            Command::Descend => {
+                // go down stairs, if the player is on them
+                let player_on_stairs = self.objects.iter().any(|(_, object)| {
+                    object.pos() == self.objects[PLAYER].pos() && object.name == "stairs"
+                });
+                if player_on_stairs {
+                    next_level(&mut self.objects, &mut self.game, &mut self.fov, &self.templates);
+                }
+                false
+            }
+            Command::Ascend => {
+                // go back up, if the player is on the up stairs
+                let player_on_stairs = self.objects.iter().any(|(_, object)| {
+                    object.pos() == self.objects[PLAYER].pos() && object.name == "up stairs"
+                });
+                if player_on_stairs {
+                    previous_level(&mut self.objects, &mut self.game, &mut self.fov, &self.templates);
+                }
+                false
+            }
+            Command::CloseDoor { dx, dy } => {
+                close_door(dx.signum(), dy.signum(), &self.objects, &mut self.game, &mut self.fov)
+            }
+            Command::CloseNearbyDoor => close_nearby_door(&self.objects, &mut self.game, &mut self.fov),
+            Command::Search => {
+                let chance = search_chance(&self.objects);
+                if search_around(chance, &self.objects, &mut self.game, &mut self.fov) == 0 {
+                    self.game.log.add("You search around you, but find nothing.", colors::LIGHT_GREY);
+                }
+                true
+            }
+            Command::LevelUp(stat) => {
+                if self.level_up_pending() {
+                    level_up(stat, &mut self.objects, &mut self.game);
+                }
+                false
+            }
+        }
+    }
+}
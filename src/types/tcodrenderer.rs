@@ -0,0 +1,201 @@
+use tcod::console::{self, Root, Console, BackgroundFlag, TextAlignment};
+use tcod::image::{self, Image};
+use tcod::input::{self, Event};
+
+use super::colors::{self, Color};
+use super::{FontInfo, FontLayout, FontType, Input, Key, KeyCode, Mouse, Renderer};
+use crate::r#const::*;
+
+/// Draws the game in a libtcod (SDL) window
+pub struct TcodRenderer {
+    root: Root,
+    font: FontInfo,
+    // the last image drawn, so it isn't loaded again every frame
+    image: Option<(String, Image)>,
+}
+
+impl TcodRenderer {
+    pub fn new(font: FontInfo) -> Self {
+        let root = TcodRenderer::open(&font, false);
+        TcodRenderer { root, font, image: None }
+    }
+
+    /// open (or reopen) the window using the given font
+    fn open(font: &FontInfo, fullscreen: bool) -> Root {
+        let root = Root::initializer()
+            .font(font.path(), match font.layout {
+                FontLayout::AsciiInCol => console::FontLayout::AsciiInCol,
+                FontLayout::AsciiInRow => console::FontLayout::AsciiInRow,
+                FontLayout::Tcod => console::FontLayout::Tcod,
+            })
+            .font_type(match font.font_type {
+                FontType::Default => console::FontType::Default,
+                FontType::Greyscale => console::FontType::Greyscale,
+            })
+            .font_dimensions(font.columns, font.rows)
+            .size(SCREEN_WIDTH, SCREEN_HEIGHT)
+            .fullscreen(fullscreen)
+            .title("Rouge")
+            .init();
+        // Limit FPS here
+        tcod::system::set_fps(LIMIT_FPS);
+        root
+    }
+}
+
+impl Renderer for TcodRenderer {
+    fn clear(&mut self) {
+        self.root.set_default_background(tcod_color(colors::BLACK));
+        self.root.clear();
+    }
+
+    fn put_glyph(&mut self, x: i32, y: i32, glyph: char, fg: Color, bg: Option<Color>) {
+        let glyph = self.font.glyph(glyph);
+        match bg {
+            Some(bg) => self.root.put_char_ex(x, y, glyph, tcod_color(fg), tcod_color(bg)),
+            None => {
+                self.root.set_default_foreground(tcod_color(fg));
+                self.root.put_char(x, y, glyph, BackgroundFlag::None);
+            }
+        }
+    }
+
+    fn print(&mut self, x: i32, y: i32, text: &str, fg: Color) {
+        self.root.set_default_foreground(tcod_color(fg));
+        self.root.print_ex(x, y, BackgroundFlag::None, TextAlignment::Left, text);
+    }
+
+    fn fill(&mut self, x: i32, y: i32, width: i32, height: i32, bg: Color) {
+        self.root.set_default_background(tcod_color(bg));
+        self.root.rect(x, y, width, height, false, BackgroundFlag::Set);
+    }
+
+    fn flush(&mut self) {
+        self.root.flush();
+    }
+
+    fn poll_input(&mut self) -> Option<Input> {
+        match input::check_for_event(input::MOUSE | input::KEY_PRESS) {
+            Some((_, Event::Mouse(m))) => Some(Input::Mouse(Mouse {
+                cx: m.cx as i32,
+                cy: m.cy as i32,
+                lbutton_pressed: m.lbutton_pressed,
+                rbutton_pressed: m.rbutton_pressed,
+            })),
+            Some((_, Event::Key(k))) => Some(Input::Key(from_tcod_key(k))),
+            None => None,
+        }
+    }
+
+    fn wait_for_key(&mut self) -> Key {
+        from_tcod_key(self.root.wait_for_keypress(true))
+    }
+
+    fn is_closed(&self) -> bool {
+        self.root.window_closed()
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        let fullscreen = self.root.is_fullscreen();
+        self.root.set_fullscreen(!fullscreen);
+    }
+
+    fn draw_image(&mut self, path: &str) {
+        let loaded = self.image.as_ref().map_or(false, |&(ref loaded_path, _)| loaded_path == path);
+        if !loaded {
+            self.image = Image::from_file(path).ok().map(|image| (path.to_string(), image));
+        }
+        if let Some((_, ref image)) = self.image {
+            // at twice the regular console resolution
+            image::blit_2x(image, (0, 0), (-1, -1), &mut self.root, (0, 0));
+        }
+    }
+
+    fn font(&self) -> Option<&FontInfo> {
+        Some(&self.font)
+    }
+
+    fn set_font(&mut self, font: &FontInfo) {
+        // the window has to be reopened to change its font
+        let fullscreen = self.root.is_fullscreen();
+        self.font = font.clone();
+        self.root = TcodRenderer::open(&self.font, fullscreen);
+    }
+}
+
+fn tcod_color(color: Color) -> tcod::Color {
+    tcod::Color { r: color.r, g: color.g, b: color.b }
+}
+
+fn from_tcod_key(key: input::Key) -> Key {
+    use tcod::input::KeyCode as Tcod;
+    let code = match key.code {
+        Tcod::NoKey => KeyCode::NoKey,
+        Tcod::Escape => KeyCode::Escape,
+        Tcod::Backspace => KeyCode::Backspace,
+        Tcod::Tab => KeyCode::Tab,
+        Tcod::Enter => KeyCode::Enter,
+        Tcod::Shift => KeyCode::Shift,
+        Tcod::Control => KeyCode::Control,
+        Tcod::Alt => KeyCode::Alt,
+        Tcod::Pause => KeyCode::Pause,
+        Tcod::CapsLock => KeyCode::CapsLock,
+        Tcod::PageUp => KeyCode::PageUp,
+        Tcod::PageDown => KeyCode::PageDown,
+        Tcod::End => KeyCode::End,
+        Tcod::Home => KeyCode::Home,
+        Tcod::Up => KeyCode::Up,
+        Tcod::Left => KeyCode::Left,
+        Tcod::Right => KeyCode::Right,
+        Tcod::Down => KeyCode::Down,
+        Tcod::PrintScreen => KeyCode::PrintScreen,
+        Tcod::Insert => KeyCode::Insert,
+        Tcod::Delete => KeyCode::Delete,
+        Tcod::LeftWin => KeyCode::LeftWin,
+        Tcod::RightWin => KeyCode::RightWin,
+        Tcod::Apps => KeyCode::Apps,
+        Tcod::Number0 => KeyCode::Number0,
+        Tcod::Number1 => KeyCode::Number1,
+        Tcod::Number2 => KeyCode::Number2,
+        Tcod::Number3 => KeyCode::Number3,
+        Tcod::Number4 => KeyCode::Number4,
+        Tcod::Number5 => KeyCode::Number5,
+        Tcod::Number6 => KeyCode::Number6,
+        Tcod::Number7 => KeyCode::Number7,
+        Tcod::Number8 => KeyCode::Number8,
+        Tcod::Number9 => KeyCode::Number9,
+        Tcod::NumPad0 => KeyCode::NumPad0,
+        Tcod::NumPad1 => KeyCode::NumPad1,
+        Tcod::NumPad2 => KeyCode::NumPad2,
+        Tcod::NumPad3 => KeyCode::NumPad3,
+        Tcod::NumPad4 => KeyCode::NumPad4,
+        Tcod::NumPad5 => KeyCode::NumPad5,
+        Tcod::NumPad6 => KeyCode::NumPad6,
+        Tcod::NumPad7 => KeyCode::NumPad7,
+        Tcod::NumPad8 => KeyCode::NumPad8,
+        Tcod::NumPad9 => KeyCode::NumPad9,
+        Tcod::NumPadAdd => KeyCode::NumPadAdd,
+        Tcod::NumPadSubtract => KeyCode::NumPadSubtract,
+        Tcod::NumPadDivide => KeyCode::NumPadDivide,
+        Tcod::NumPadMultiply => KeyCode::NumPadMultiply,
+        Tcod::NumPadDecimal => KeyCode::NumPadDecimal,
+        Tcod::NumPadEnter => KeyCode::NumPadEnter,
+        Tcod::F1 => KeyCode::F1,
+        Tcod::F2 => KeyCode::F2,
+        Tcod::F3 => KeyCode::F3,
+        Tcod::F4 => KeyCode::F4,
+        Tcod::F5 => KeyCode::F5,
+        Tcod::F6 => KeyCode::F6,
+        Tcod::F7 => KeyCode::F7,
+        Tcod::F8 => KeyCode::F8,
+        Tcod::F9 => KeyCode::F9,
+        Tcod::F10 => KeyCode::F10,
+        Tcod::F11 => KeyCode::F11,
+        Tcod::F12 => KeyCode::F12,
+        Tcod::NumLock => KeyCode::NumLock,
+        Tcod::ScrollLock => KeyCode::ScrollLock,
+        Tcod::Spacebar => KeyCode::Spacebar,
+        Tcod::Char => KeyCode::Char,
+    };
+    Key { code, printable: key.printable, alt: key.alt, ctrl: key.ctrl, shift: key.shift }
+}
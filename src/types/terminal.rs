@@ -0,0 +1,315 @@
+use std::collections::VecDeque;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+use std::mem;
+
+use super::colors::{self, Color};
+use super::{Key, KeyCode};
+
+use super::{Input, Renderer, ascii_glyph};
+use crate::r#const::*;
+
+#[derive(Clone, Copy, PartialEq)]
+struct Cell {
+    glyph: char,
+    fg: Color,
+    bg: Color,
+}
+
+const BLANK: Cell = Cell { glyph: ' ', fg: colors::WHITE, bg: colors::BLACK };
+
+/// Draws the game in the terminal it was started from, with ANSI escape
+/// codes, so it can be played over SSH. The terminal needs to be at least
+/// `SCREEN_WIDTH` x `SCREEN_HEIGHT` and support 24-bit colour.
+pub struct TerminalRenderer {
+    cells: Vec<Cell>,
+    // what's on the terminal now, so only changed cells are redrawn
+    shown: Vec<Option<Cell>>,
+    // the terminal settings to put back when the game exits
+    original_termios: libc::termios,
+    input: VecDeque<u8>,
+    closed: bool,
+}
+
+impl TerminalRenderer {
+    /// Switch the terminal to raw mode and take over the screen. Fails if
+    /// stdin isn't a terminal.
+    pub fn new() -> io::Result<Self> {
+        let original_termios = unsafe {
+            let mut termios = mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut raw = termios;
+            libc::cfmakeraw(&mut raw);
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            termios
+        };
+        let size = (SCREEN_WIDTH * SCREEN_HEIGHT) as usize;
+        let renderer = TerminalRenderer {
+            cells: vec![BLANK; size],
+            shown: vec![None; size],
+            original_termios,
+            input: VecDeque::new(),
+            closed: false,
+        };
+        // switch to the alternate screen and hide the cursor
+        write_stdout("\x1b[?1049h\x1b[?25l\x1b[2J");
+        Ok(renderer)
+    }
+
+    fn cell_mut(&mut self, x: i32, y: i32) -> Option<&mut Cell> {
+        if x < 0 || y < 0 || x >= SCREEN_WIDTH || y >= SCREEN_HEIGHT {
+            return None;
+        }
+        self.cells.get_mut((y * SCREEN_WIDTH + x) as usize)
+    }
+
+    /// Read whatever input is waiting, waiting up to `timeout_ms` for some
+    /// to arrive (or forever, if it's negative)
+    fn read_input(&mut self, timeout_ms: i32) {
+        let mut poll_fd = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+        let ready = unsafe { libc::poll(&mut poll_fd, 1, timeout_ms) };
+        if ready <= 0 {
+            return;
+        }
+        let mut buffer = [0u8; 64];
+        let read = unsafe {
+            libc::read(libc::STDIN_FILENO, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len())
+        };
+        if read <= 0 {
+            self.closed = true;
+            return;
+        }
+        self.input.extend(&buffer[..read as usize]);
+    }
+
+    /// Turn the next key's bytes into a `Key`, the way tcod would report it
+    fn next_key(&mut self) -> Option<Key> {
+        // a lone Escape could be the start of an escape sequence that's still on its way
+        if self.input.len() == 1 && self.input[0] == 0x1b {
+            self.read_input(10);
+        }
+        next_key(&mut self.input)
+    }
+}
+
+impl Renderer for TerminalRenderer {
+    fn clear(&mut self) {
+        for cell in &mut self.cells {
+            *cell = BLANK;
+        }
+    }
+
+    fn put_glyph(&mut self, x: i32, y: i32, glyph: char, fg: Color, bg: Option<Color>) {
+        // a terminal has no custom font, so draws everything in ASCII
+        let glyph = ascii_glyph(glyph);
+        // anything else the terminal can't show
+        let glyph = if glyph.is_control() { '?' } else { glyph };
+        if let Some(cell) = self.cell_mut(x, y) {
+            cell.glyph = glyph;
+            cell.fg = fg;
+            if let Some(bg) = bg {
+                cell.bg = bg;
+            }
+        }
+    }
+
+    fn print(&mut self, x: i32, y: i32, text: &str, fg: Color) {
+        for (i, c) in text.chars().enumerate() {
+            if let Some(cell) = self.cell_mut(x + i as i32, y) {
+                cell.glyph = c;
+                cell.fg = fg;
+            }
+        }
+    }
+
+    fn fill(&mut self, x: i32, y: i32, width: i32, height: i32, bg: Color) {
+        for cell_y in y..(y + height) {
+            for cell_x in x..(x + width) {
+                if let Some(cell) = self.cell_mut(cell_x, cell_y) {
+                    cell.bg = bg;
+                }
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        let mut out = String::new();
+        let mut colors: Option<(Color, Color)> = None;
+        let mut next_position = None;
+        for (i, &cell) in self.cells.iter().enumerate() {
+            if self.shown[i] == Some(cell) {
+                continue;
+            }
+            let (x, y) = (i as i32 % SCREEN_WIDTH, i as i32 / SCREEN_WIDTH);
+            if next_position != Some(i) {
+                write!(out, "\x1b[{};{}H", y + 1, x + 1).unwrap();
+            }
+            if colors != Some((cell.fg, cell.bg)) {
+                write!(out, "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+                       cell.fg.r, cell.fg.g, cell.fg.b, cell.bg.r, cell.bg.g, cell.bg.b).unwrap();
+                colors = Some((cell.fg, cell.bg));
+            }
+            out.push(cell.glyph);
+            next_position = Some(i + 1);
+            self.shown[i] = Some(cell);
+        }
+        write_stdout(&out);
+    }
+
+    fn poll_input(&mut self) -> Option<Input> {
+        if self.input.is_empty() {
+            // waiting here for a frame's worth of time keeps the game loop
+            // from spinning, like tcod's FPS limit does
+            self.read_input(1000 / LIMIT_FPS);
+        }
+        self.next_key().map(Input::Key)
+    }
+
+    fn wait_for_key(&mut self) -> Key {
+        loop {
+            if self.closed {
+                return Key { code: KeyCode::Escape, ..Default::default() };
+            }
+            if self.input.is_empty() {
+                self.read_input(-1);
+            }
+            if let Some(key) = self.next_key() {
+                return key;
+            }
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+impl Drop for TerminalRenderer {
+    fn drop(&mut self) {
+        // reset the colours, show the cursor and go back to the normal screen
+        write_stdout("\x1b[0m\x1b[?25h\x1b[?1049l");
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original_termios);
+        }
+    }
+}
+
+fn write_stdout(text: &str) {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    stdout.write_all(text.as_bytes()).ok();
+    stdout.flush().ok();
+}
+
+/// Turn the next key's bytes in `input` into a `Key`, the way tcod would report it
+fn next_key(input: &mut VecDeque<u8>) -> Option<Key> {
+    let byte = input.pop_front()?;
+    let key = |code: KeyCode| Some(Key { code, ..Default::default() });
+    let character = |c: char| Some(Key { code: KeyCode::Char, printable: c, ..Default::default() });
+    match byte {
+        0x1b => {
+            // a lone Escape, the start of an escape sequence, or Alt and a key
+            match input.front().cloned() {
+                None => key(KeyCode::Escape),
+                Some(b'[') | Some(b'O') => escape_sequence(input),
+                Some(_) => next_key(input).map(|k| Key { alt: true, ..k }),
+            }
+        }
+        b'\r' | b'\n' => key(KeyCode::Enter),
+        0x7f | 0x08 => key(KeyCode::Backspace),
+        b'\t' => key(KeyCode::Tab),
+        b' ' => Some(Key { code: KeyCode::Spacebar, printable: ' ', ..Default::default() }),
+        // Ctrl and a letter, apart from the ones that are keys of their own above
+        0x01..=0x07 | 0x0b | 0x0c | 0x0e..=0x1a => {
+            let letter = (b'a' + byte - 1) as char;
+            Some(Key { code: KeyCode::Char, printable: letter, ctrl: true, ..Default::default() })
+        }
+        0x21..=0x7e => character(byte as char),
+        _ => {
+            // the rest of a UTF-8 character
+            let length = match byte {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => return None,
+            };
+            let mut bytes = vec![byte];
+            while bytes.len() < length {
+                bytes.push(input.pop_front()?);
+            }
+            String::from_utf8(bytes).ok()?.chars().next().and_then(character)
+        }
+    }
+}
+
+/// Keys like the arrows send `ESC [ A` and so on
+fn escape_sequence(input: &mut VecDeque<u8>) -> Option<Key> {
+    let mut sequence = String::new();
+    while let Some(byte) = input.pop_front() {
+        sequence.push(byte as char);
+        // the sequence ends with a letter or ~
+        if sequence.len() > 1 && ((byte as char).is_ascii_alphabetic() || byte == b'~') {
+            break;
+        }
+    }
+    let code = match &sequence[..] {
+        "[A" | "OA" => KeyCode::Up,
+        "[B" | "OB" => KeyCode::Down,
+        "[C" | "OC" => KeyCode::Right,
+        "[D" | "OD" => KeyCode::Left,
+        "[H" | "OH" | "[1~" | "[7~" => KeyCode::Home,
+        "[F" | "OF" | "[4~" | "[8~" => KeyCode::End,
+        "[5~" => KeyCode::PageUp,
+        "[6~" => KeyCode::PageDown,
+        "[2~" => KeyCode::Insert,
+        "[3~" => KeyCode::Delete,
+        // the middle of the numpad, with num lock off
+        "[E" | "OE" | "[G" => KeyCode::NumPad5,
+        "OP" => KeyCode::F1,
+        "OQ" => KeyCode::F2,
+        "OR" => KeyCode::F3,
+        "OS" => KeyCode::F4,
+        _ => return None,
+    };
+    Some(Key { code, ..Default::default() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(bytes: &[u8]) -> Vec<Key> {
+        let mut input: VecDeque<u8> = bytes.iter().cloned().collect();
+        let mut keys = vec![];
+        while !input.is_empty() {
+            keys.extend(next_key(&mut input));
+        }
+        keys
+    }
+
+    fn key(code: KeyCode) -> Key {
+        Key { code, ..Default::default() }
+    }
+
+    #[test]
+    fn escape_sequences_are_one_key() {
+        assert_eq!(keys(b"\x1b[5~"), vec![key(KeyCode::PageUp)]);
+        assert_eq!(keys(b"\x1bOP"), vec![key(KeyCode::F1)]);
+        assert_eq!(keys(b"\x1b[A\x1b[6~"), vec![key(KeyCode::Up), key(KeyCode::PageDown)]);
+    }
+
+    #[test]
+    fn a_lone_escape_is_the_escape_key() {
+        assert_eq!(keys(b"\x1b"), vec![key(KeyCode::Escape)]);
+    }
+
+    #[test]
+    fn escape_and_a_key_is_alt() {
+        let g = Key { code: KeyCode::Char, printable: 'g', alt: true, ..Default::default() };
+        assert_eq!(keys(b"\x1bg"), vec![g]);
+    }
+}
@@ -1,16 +1,193 @@
+use super::*;
+
+use crate::r#const::*;
+
+/// The state of a door
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Door {
+    Open,
+    Closed,
+    /// closed, and it takes a key to open it
+    Locked,
+    /// looks just like the wall around it until someone finds it
+    Secret,
+}
+
+/// The different traps hidden in the floor
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TrapKind {
+    /// shoots a dart at whoever steps on it
+    Dart,
+    /// moves whoever steps on it somewhere else on the level
+    Teleport,
+    /// wakes every monster on the level and sends them after the player
+    Alarm,
+    /// drops whoever steps on it to the level below
+    Trapdoor,
+}
+
+impl TrapKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TrapKind::Dart => "dart trap",
+            TrapKind::Teleport => "teleport trap",
+            TrapKind::Alarm => "alarm trap",
+            TrapKind::Trapdoor => "trapdoor",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            TrapKind::Dart => colors::LIGHT_GREY,
+            TrapKind::Teleport => colors::LIGHT_MAGENTA,
+            TrapKind::Alarm => colors::LIGHT_YELLOW,
+            TrapKind::Trapdoor => colors::LIGHT_SEPIA,
+        }
+    }
+}
+
+/// A trap, which can't be seen until it's been found or set off
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Trap {
+    pub kind: TrapKind,
+    pub hidden: bool,
+}
+
+/// What a tile is made of, which decides how it looks and what happens to
+/// whoever walks onto it
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TileKind {
+    Floor,
+    Wall,
+    /// slow going
+    ShallowWater,
+    /// slow going, and heavy things get swept out of your pack
+    DeepWater,
+    /// burns
+    Lava,
+    /// a drop straight down to the next level
+    Chasm,
+    /// slow going
+    Rubble,
+}
+
+/// What happens to something that walks onto a tile
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TileEffect {
+    /// unequipped weapons and armour may be lost
+    SweepAway,
+    /// take this much damage
+    Burn(i32),
+    /// fall to the next level
+    Fall,
+}
+
+impl TileKind {
+    pub fn glyph(self) -> char {
+        match self {
+            TileKind::Floor => FLOOR,
+            TileKind::Wall => WALL,
+            TileKind::ShallowWater | TileKind::DeepWater | TileKind::Lava => '~',
+            TileKind::Chasm => ':',
+            TileKind::Rubble => ',',
+        }
+    }
+
+    /// the color to draw it in while it's in view
+    pub fn lit_color(self) -> Color {
+        match self {
+            TileKind::Floor => COLOR_LIGHT_GROUND,
+            TileKind::Wall => COLOR_LIGHT_WALL,
+            TileKind::ShallowWater => Color { r: 90, g: 140, b: 230 },
+            TileKind::DeepWater => Color { r: 30, g: 60, b: 200 },
+            TileKind::Lava => Color { r: 255, g: 90, b: 0 },
+            TileKind::Chasm => Color { r: 60, g: 40, b: 80 },
+            TileKind::Rubble => Color { r: 150, g: 130, b: 100 },
+        }
+    }
+
+    /// the color to draw it in once it's been explored but is out of view
+    pub fn dark_color(self) -> Color {
+        match self {
+            TileKind::Floor => COLOR_DARK_GROUND,
+            TileKind::Wall => COLOR_DARK_WALL,
+            TileKind::ShallowWater => Color { r: 50, g: 70, b: 120 },
+            TileKind::DeepWater => Color { r: 20, g: 30, b: 100 },
+            TileKind::Lava => Color { r: 120, g: 50, b: 20 },
+            TileKind::Chasm => Color { r: 30, g: 20, b: 40 },
+            TileKind::Rubble => Color { r: 80, g: 75, b: 65 },
+        }
+    }
+
+    /// how many turns it takes the player to walk onto it
+    pub fn walk_cost(self) -> u32 {
+        match self {
+            TileKind::ShallowWater | TileKind::DeepWater | TileKind::Rubble => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn blocks(self) -> bool {
+        self == TileKind::Wall
+    }
+
+    pub fn blocks_sight(self) -> bool {
+        self == TileKind::Wall
+    }
+
+    pub fn effect(self) -> Option<TileEffect> {
+        match self {
+            TileKind::DeepWater => Some(TileEffect::SweepAway),
+            TileKind::Lava => Some(TileEffect::Burn(LAVA_DAMAGE)),
+            TileKind::Chasm => Some(TileEffect::Fall),
+            _ => None,
+        }
+    }
+
+    /// whether monsters will walk onto it, and levels count it as a way through
+    pub fn is_safe(self) -> bool {
+        matches!(self.effect(), None | Some(TileEffect::SweepAway))
+    }
+}
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Tile {
     pub blocked: bool,
     pub block_sight: bool,
     pub explored: bool,
+    /// whether this tile is a door, and if so whether it's open
+    pub door: Option<Door>,
+    pub kind: TileKind,
+    /// a trap in the floor, which goes off when anyone steps on it
+    pub trap: Option<Trap>,
 }
 
 impl Tile {
+    pub fn new(kind: TileKind) -> Self {
+        Tile{blocked: kind.blocks(), explored: false, block_sight: kind.blocks_sight(), door: None, kind,
+             trap: None}
+    }
+
     pub fn empty() -> Self {
-        Tile{blocked: false, explored: false, block_sight: false}
+        Tile::new(TileKind::Floor)
     }
 
     pub fn wall() -> Self {
-        Tile{blocked: true, explored: false, block_sight: true}
+        Tile::new(TileKind::Wall)
+    }
+
+    /// a door, which blocks movement and sight unless it's open. Secret
+    /// doors are made of wall, as far as anyone can tell.
+    pub fn door(door: Door) -> Self {
+        let closed = door != Door::Open;
+        let kind = if door == Door::Secret { TileKind::Wall } else { TileKind::Floor };
+        Tile{blocked: closed, explored: false, block_sight: closed, door: Some(door), kind,
+             trap: None}
+    }
+
+    /// Whether it can be walked onto, opening or unlocking a door on the way
+    /// if need be, without coming to any harm
+    pub fn passable(&self) -> bool {
+        (!self.blocked || self.door.is_some()) && self.kind.is_safe()
     }
 }
@@ -0,0 +1,58 @@
+//! The same seed has to give the same game, roll for roll.
+
+use std::error::Error;
+
+use rouge::func::*;
+use rouge::types::*;
+
+// a bit of everything: walking about, waiting, picking up and taking the stairs
+const COMMANDS: &[Command] = &[
+    Command::Move { dx: 1, dy: 0 },
+    Command::Move { dx: 0, dy: 1 },
+    Command::Wait,
+    Command::PickUp,
+    Command::Move { dx: -1, dy: -1 },
+    Command::Search,
+    Command::Descend,
+    Command::Move { dx: 1, dy: 1 },
+    Command::Wait,
+];
+
+/// everything about a game that the seed decides, in a comparable form
+fn snapshot(state: &GameState) -> Result<(String, String, String), Box<dyn Error>> {
+    Ok((serde_json::to_string(&state.game.map)?,
+        serde_json::to_string(&state.objects)?,
+        serde_json::to_string(&state.game.rng)?))
+}
+
+fn play(seed: u64) -> Result<GameState, Box<dyn Error>> {
+    let mut state = GameState::new(seed, load_templates()?);
+    for &command in COMMANDS {
+        state.step(command);
+    }
+    Ok(state)
+}
+
+#[test]
+fn same_seed_same_game() -> Result<(), Box<dyn Error>> {
+    for seed in 0..20 {
+        let (first, second) = (GameState::new(seed, load_templates()?), GameState::new(seed, load_templates()?));
+        assert_eq!(snapshot(&first)?, snapshot(&second)?, "new games from seed {} differ", seed);
+
+        let (first, second) = (play(seed)?, play(seed)?);
+        assert_eq!(snapshot(&first)?, snapshot(&second)?, "games from seed {} went differently", seed);
+        assert_eq!(first.game.log, second.game.log);
+    }
+    Ok(())
+}
+
+#[test]
+fn different_seeds_differ() -> Result<(), Box<dyn Error>> {
+    for seed in 0..20 {
+        let (first, second) = (play(seed)?, play(seed + 1000)?);
+        let (first, second) = (snapshot(&first)?, snapshot(&second)?);
+        assert_ne!(first.0, second.0, "seeds {} and {} made the same map", seed, seed + 1000);
+        assert_ne!(first.2, second.2, "seeds {} and {} left the same rng", seed, seed + 1000);
+    }
+    Ok(())
+}
@@ -0,0 +1,233 @@
+//! Drives `GameState` the way a frontend would, with no window at all.
+
+use std::error::Error;
+
+use rouge::func::*;
+use rouge::r#const::*;
+use rouge::types::*;
+
+/// a new game with nothing on the map but the player, and plain floor to
+/// their east so moves and drops always land somewhere known
+fn quiet_game(seed: u64) -> Result<GameState, Box<dyn Error>> {
+    let mut state = GameState::new(seed, load_templates()?);
+    state.objects.retain(|id, _| id == PLAYER);
+    let (x, y) = state.player().pos();
+    state.game.map[(x + 1) as usize][y as usize] = Tile::empty();
+    Ok(state)
+}
+
+fn spawn_item(state: &mut GameState, name: &str, x: i32, y: i32) -> EntityId {
+    let item = state.templates.items.iter()
+        .find(|item| item.name == name)
+        .unwrap_or_else(|| panic!("no {} in data/items.json", name))
+        .spawn(x, y);
+    state.objects.spawn(item)
+}
+
+fn spawn_monster(state: &mut GameState, name: &str, x: i32, y: i32) -> EntityId {
+    let monster = state.templates.monsters.iter()
+        .find(|monster| monster.name == name)
+        .unwrap_or_else(|| panic!("no {} in data/monsters.json", name))
+        .spawn(x, y, state.game.dungeon_level);
+    state.objects.spawn(monster)
+}
+
+fn xp(state: &GameState) -> i32 {
+    state.player().fighter.map_or(0, |f| f.xp)
+}
+
+fn hp(state: &GameState) -> i32 {
+    state.player().fighter.map_or(0, |f| f.hp)
+}
+
+#[test]
+fn moving_takes_a_turn() -> Result<(), Box<dyn Error>> {
+    let mut state = quiet_game(1)?;
+    let (x, y) = state.player().pos();
+    let events = state.step(Command::Move { dx: 1, dy: 0 });
+    assert!(events.contains(&GameEvent::TurnTaken));
+    assert_eq!(state.player().pos(), (x + 1, y));
+    assert_eq!(state.game.history, vec![Command::Move { dx: 1, dy: 0 }]);
+    Ok(())
+}
+
+#[test]
+fn walking_into_a_wall_stays_put() -> Result<(), Box<dyn Error>> {
+    let mut state = quiet_game(2)?;
+    let (x, y) = state.player().pos();
+    state.game.map[(x - 1) as usize][y as usize] = Tile::wall();
+    state.step(Command::Move { dx: -1, dy: 0 });
+    assert_eq!(state.player().pos(), (x, y));
+    Ok(())
+}
+
+#[test]
+fn waiting_lets_monsters_attack() -> Result<(), Box<dyn Error>> {
+    let mut state = quiet_game(3)?;
+    let (x, y) = state.player().pos();
+    let troll = state.templates.monsters.iter().find(|monster| monster.name == "troll").unwrap()
+        .spawn(x + 1, y, 1);
+    state.objects.spawn(troll);
+    let before = hp(&state);
+    let events = state.step(Command::Wait);
+    assert!(events.contains(&GameEvent::TurnTaken));
+    assert!(hp(&state) < before, "the troll should have hit the player");
+    Ok(())
+}
+
+#[test]
+fn picking_up_and_drinking_a_potion() -> Result<(), Box<dyn Error>> {
+    let mut state = quiet_game(4)?;
+    let (x, y) = state.player().pos();
+    spawn_item(&mut state, "healing potion", x, y);
+    // the player starts out with a dagger
+    let carried = state.game.inventory.len();
+
+    let events = state.step(Command::PickUp);
+    assert!(!events.contains(&GameEvent::TurnTaken));
+    assert_eq!(state.game.inventory.len(), carried + 1);
+    assert_eq!(state.objects.with_item().count(), 0);
+
+    state.objects[PLAYER].fighter.as_mut().unwrap().hp = 10;
+    state.step(Command::UseItem { inventory_id: carried, target: None });
+    assert_eq!(hp(&state), 10 + HEAL_AMOUNT);
+    assert_eq!(state.game.inventory.len(), carried, "the potion should be used up");
+    Ok(())
+}
+
+#[test]
+fn out_of_range_commands_are_ignored() -> Result<(), Box<dyn Error>> {
+    let mut state = quiet_game(5)?;
+    let carried = state.game.inventory.len();
+    let events = state.step(Command::UseItem { inventory_id: carried, target: None });
+    assert!(!events.contains(&GameEvent::TurnTaken));
+    state.step(Command::DropItem { inventory_id: carried });
+    assert_eq!(state.game.inventory.len(), carried);
+    Ok(())
+}
+
+#[test]
+fn descending_the_stairs() -> Result<(), Box<dyn Error>> {
+    let mut state = GameState::new(6, load_templates()?);
+    // only on the stairs
+    state.step(Command::Descend);
+    assert_eq!(state.game.dungeon_level, 1);
+
+    let stairs = state.objects.iter()
+        .find(|&(_, object)| object.name == "stairs")
+        .map(|(_, object)| object.pos())
+        .unwrap();
+    state.objects[PLAYER].set_pos(stairs.0, stairs.1);
+    let events = state.step(Command::Descend);
+    assert!(events.contains(&GameEvent::LevelChanged(2)));
+    assert!(events.contains(&GameEvent::SaveDue), "every new level should be saved");
+    assert_eq!(state.game.dungeon_level, 2);
+    // arriving on the stairs back up
+    let player_pos = state.player().pos();
+    assert!(state.objects.iter().any(|(_, object)| object.name == "up stairs" && object.pos() == player_pos));
+    Ok(())
+}
+
+#[test]
+fn saving_every_so_often() -> Result<(), Box<dyn Error>> {
+    let mut state = quiet_game(9)?;
+    for _ in 1..AUTOSAVE_TURNS {
+        assert!(!state.step(Command::Wait).contains(&GameEvent::SaveDue));
+    }
+    // commands that don't take a turn don't count
+    assert!(!state.step(Command::PickUp).contains(&GameEvent::SaveDue));
+    assert!(state.step(Command::Wait).contains(&GameEvent::SaveDue));
+    assert!(!state.step(Command::Wait).contains(&GameEvent::SaveDue), "the count starts again after a save");
+    Ok(())
+}
+
+#[test]
+fn levelling_up() -> Result<(), Box<dyn Error>> {
+    let mut state = quiet_game(7)?;
+    let power = state.player().power(&state.game);
+
+    // not enough experience yet
+    state.step(Command::LevelUp(Stat::Strength));
+    assert_eq!(state.player().level, 1);
+
+    let xp = level_up_xp(state.player());
+    state.objects[PLAYER].fighter.as_mut().unwrap().xp = xp;
+    assert!(state.level_up_pending());
+    assert!(state.step(Command::Wait).contains(&GameEvent::LevelUpReady));
+
+    state.step(Command::LevelUp(Stat::Strength));
+    assert_eq!(state.player().level, 2);
+    assert_eq!(state.player().power(&state.game), power + 1);
+    assert!(!state.level_up_pending());
+    Ok(())
+}
+
+#[test]
+fn dying_ends_the_game() -> Result<(), Box<dyn Error>> {
+    let mut state = quiet_game(8)?;
+    let (x, y) = state.player().pos();
+    state.game.map[(x + 1) as usize][y as usize] = Tile::new(TileKind::Lava);
+    state.objects[PLAYER].fighter.as_mut().unwrap().hp = 1;
+
+    let events = state.step(Command::Move { dx: 1, dy: 0 });
+    assert!(events.contains(&GameEvent::PlayerDied("lava".into())));
+    assert!(!state.player().alive);
+
+    // nothing more happens once the player is dead
+    assert!(state.step(Command::Wait).is_empty());
+    Ok(())
+}
+
+#[test]
+fn monsters_killed_by_traps_give_experience() -> Result<(), Box<dyn Error>> {
+    let mut state = quiet_game(9)?;
+    let (x, y) = state.player().pos();
+    let orc = spawn_monster(&mut state, "orc", x + 1, y);
+    state.objects[orc].fighter.as_mut().unwrap().hp = 1;
+    state.game.map[(x + 1) as usize][y as usize].trap = Some(Trap { kind: TrapKind::Dart, hidden: true });
+    let orc_xp = state.objects[orc].fighter.map_or(0, |f| f.xp);
+    let before = xp(&state);
+
+    spring_trap(orc, &mut state.objects, &mut state.game, &mut state.fov, &state.templates);
+    assert!(!state.objects[orc].alive);
+    assert!(orc_xp > 0);
+    assert_eq!(xp(&state), before + orc_xp);
+    Ok(())
+}
+
+#[test]
+fn monsters_burned_up_in_lava_give_experience() -> Result<(), Box<dyn Error>> {
+    let mut state = quiet_game(10)?;
+    let (x, y) = state.player().pos();
+    let troll = spawn_monster(&mut state, "troll", x + 1, y);
+    state.objects[troll].fighter.as_mut().unwrap().hp = 1;
+    state.game.map[(x + 1) as usize][y as usize] = Tile::new(TileKind::Lava);
+    let troll_xp = state.objects[troll].fighter.map_or(0, |f| f.xp);
+    let before = xp(&state);
+
+    monster_enters_tile(troll, &mut state.objects, &mut state.game);
+    assert!(!state.objects[troll].alive);
+    assert_eq!(xp(&state), before + troll_xp);
+    Ok(())
+}
+
+#[test]
+fn closing_a_door_with_none_around() -> Result<(), Box<dyn Error>> {
+    let mut state = quiet_game(11)?;
+    let events = state.step(Command::CloseNearbyDoor);
+    assert!(events.contains(&GameEvent::Message("There's no open door next to you.".into(),
+                                                colors::LIGHT_GREY)));
+    assert!(!events.contains(&GameEvent::TurnTaken));
+    Ok(())
+}
+
+#[test]
+fn closing_the_one_door_around() -> Result<(), Box<dyn Error>> {
+    let mut state = quiet_game(12)?;
+    let (x, y) = state.player().pos();
+    set_door(x + 1, y, Door::Open, &mut state.game.map, &mut state.fov);
+    let events = state.step(Command::CloseNearbyDoor);
+    assert!(events.contains(&GameEvent::TurnTaken));
+    assert_eq!(state.game.map[(x + 1) as usize][y as usize].door, Some(Door::Closed));
+    Ok(())
+}
@@ -0,0 +1,41 @@
+//! Every generator has to make levels where the stairs and all the loot can
+//! be walked to from the start.
+
+use std::error::Error;
+
+use rouge::func::*;
+use rouge::types::*;
+
+const SEEDS: u64 = 200;
+const DEPTHS: u32 = 10;
+
+fn check_generator(kind: GeneratorKind) -> Result<(), Box<dyn Error>> {
+    let templates = load_templates()?;
+    for seed in 0..SEEDS {
+        let (mut objects, mut game) = new_game(seed, &templates);
+        for depth in 1..=DEPTHS {
+            let map = make_map_with(kind, &mut objects, depth, &mut game.rng, &templates);
+            let unreachable: Vec<_> = unreachable_objects(&map, &objects).into_iter()
+                .map(|id| (objects[id].name.clone(), objects[id].pos()))
+                .collect();
+            assert!(unreachable.is_empty(), "{:?} level at depth {} from seed {} has unreachable {:?}",
+                    kind, depth, seed, unreachable);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn rooms_levels_can_be_finished() -> Result<(), Box<dyn Error>> {
+    check_generator(GeneratorKind::Rooms)
+}
+
+#[test]
+fn bsp_levels_can_be_finished() -> Result<(), Box<dyn Error>> {
+    check_generator(GeneratorKind::Bsp)
+}
+
+#[test]
+fn cave_levels_can_be_finished() -> Result<(), Box<dyn Error>> {
+    check_generator(GeneratorKind::Caves)
+}